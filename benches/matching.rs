@@ -0,0 +1,167 @@
+//! Compares matching emissions with interned IDs/values against the plain `String`
+//! representation Rufus used before, on a synthetic class of a few thousand submissions.
+
+use std::collections::{BTreeSet, HashMap};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rufus::{
+    gradescope::types::Submitter,
+    rufus::{hunt, Emission, EmissionsGroup, Symbol},
+    sources::SimpleSubmission,
+};
+
+const NUM_IDS: usize = 6;
+const VALUES_PER_ID: u64 = 50;
+
+/// The previous representation: owned strings, with the ID sets rebuilt on every call.
+struct StringGroup {
+    emissions_map: HashMap<String, String>,
+}
+
+impl StringGroup {
+    fn matches_on_ids(&self, other: &StringGroup, on_ids: &BTreeSet<&String>, exact: bool) -> bool {
+        let on_ids = on_ids.iter().cloned().collect::<BTreeSet<&String>>();
+        let matches_on = on_ids.iter().all(|id| {
+            match (self.emissions_map.get(*id), other.emissions_map.get(*id)) {
+                (Some(mine), Some(theirs)) => mine == theirs,
+                _ => false,
+            }
+        });
+        if !matches_on {
+            return false;
+        }
+
+        if exact {
+            let self_ids = self.emissions_map.keys().collect::<BTreeSet<&String>>();
+            let other_ids = other.emissions_map.keys().collect::<BTreeSet<&String>>();
+            for id in self_ids
+                .intersection(&other_ids)
+                .filter(|id| !on_ids.contains(*id))
+            {
+                if self.emissions_map.get(*id) == other.emissions_map.get(*id) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Deterministic pseudo-random values (no need for a real RNG in a benchmark).
+fn synthetic_values(num_submissions: usize) -> Vec<Vec<(String, String)>> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    (0..num_submissions)
+        .map(|_| {
+            (0..NUM_IDS)
+                .map(|id| {
+                    // Matrices as text, like a linear algebra autograder would emit
+                    let value = next() % VALUES_PER_ID;
+                    (
+                        format!("emission_{}", id),
+                        format!("[[{}, 0.5], [1.25, {}]]", value, value * 3),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn submissions(num_submissions: usize) -> Vec<SimpleSubmission> {
+    (0..num_submissions)
+        .map(|i| {
+            SimpleSubmission::new(Submitter {
+                name: format!("Student {}", i),
+                sid: Some(i.to_string()),
+                email: String::new(),
+            })
+        })
+        .collect()
+}
+
+fn bench_matching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("matches_on_ids");
+    for num_submissions in [500, 2000] {
+        let values = synthetic_values(num_submissions);
+        let subs = submissions(num_submissions);
+
+        let string_groups: Vec<StringGroup> = values
+            .iter()
+            .map(|pairs| StringGroup {
+                emissions_map: pairs.iter().cloned().collect(),
+            })
+            .collect();
+        let interned_groups: Vec<EmissionsGroup> = values
+            .iter()
+            .zip(&subs)
+            .map(|(pairs, sub)| {
+                let emissions = pairs.iter().map(|(id, v)| Emission::new(id, v)).collect();
+                EmissionsGroup::new(sub, emissions)
+            })
+            .collect();
+
+        let ids: Vec<String> = (0..2).map(|id| format!("emission_{}", id)).collect();
+        let string_ids: BTreeSet<&String> = ids.iter().collect();
+        let symbol_ids: BTreeSet<Symbol> = ids.iter().map(|id| Symbol::intern(id)).collect();
+
+        // Compare every submission against the first 100, as grouping does
+        group.bench_with_input(
+            BenchmarkId::new("strings", num_submissions),
+            &string_groups,
+            |b, groups| {
+                b.iter(|| {
+                    let mut count = 0;
+                    for a in groups {
+                        for other in groups.iter().take(100) {
+                            count += a.matches_on_ids(other, &string_ids, true) as usize;
+                        }
+                    }
+                    count
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("interned", num_submissions),
+            &interned_groups,
+            |b, groups| {
+                b.iter(|| {
+                    let mut count = 0;
+                    for a in groups {
+                        for other in groups.iter().take(100) {
+                            count += a.matches_on_ids(other, Some(&symbol_ids), true) as usize;
+                        }
+                    }
+                    count
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_hunt(c: &mut Criterion) {
+    let values = synthetic_values(2000);
+    let subs = submissions(2000);
+    let groups: Vec<EmissionsGroup> = values
+        .iter()
+        .zip(&subs)
+        .map(|(pairs, sub)| {
+            let emissions = pairs.iter().map(|(id, v)| Emission::new(id, v)).collect();
+            EmissionsGroup::new(sub, emissions)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("hunt");
+    group.sample_size(10);
+    group.bench_function("k=2", |b| b.iter(|| hunt(&groups, 2, false).len()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_matching, bench_hunt);
+criterion_main!(benches);
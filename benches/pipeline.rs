@@ -0,0 +1,75 @@
+//! Measures each stage of a `hunt` run (loading, parsing emissions, and grouping) on
+//! synthetic exports of growing class sizes.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rufus::{
+    gradescope::{loaders::load_export, stream::stream_export, types::SubmissionTrait},
+    rufus::{hunt, EmissionsGroup},
+    synth::{generate, write_export, SynthConfig},
+};
+
+const CLASS_SIZES: [usize; 3] = [100, 300, 1000];
+
+fn config(num_submissions: usize) -> SynthConfig {
+    SynthConfig {
+        num_submissions,
+        rings: vec![3, 2, 2, 2],
+        ..Default::default()
+    }
+}
+
+fn bench_loading(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+    for size in CLASS_SIZES {
+        let (export, _) = generate(&config(size));
+        let path = std::env::temp_dir().join(format!("rufus-bench-{}.yml", size));
+        write_export(&export, &path).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("full", size), &path, |b, path| {
+            b.iter(|| load_export(path).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("streaming", size), &path, |b, path| {
+            b.iter(|| {
+                stream_export(path)
+                    .unwrap()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap()
+            })
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+    group.finish();
+}
+
+fn bench_parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for size in CLASS_SIZES {
+        let (export, _) = generate(&config(size));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &export, |b, export| {
+            b.iter(|| {
+                export
+                    .values()
+                    .map(|s| s.parse_emissions().len())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_grouping(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hunt");
+    group.sample_size(10);
+    for size in CLASS_SIZES {
+        let (export, _) = generate(&config(size));
+        let groups: Vec<EmissionsGroup> = export.values().map(|s| s.parse_emissions()).collect();
+        group.bench_with_input(BenchmarkId::new("k=2", size), &groups, |b, groups| {
+            b.iter(|| hunt(groups, 2, false).len())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_loading, bench_parsing, bench_grouping);
+criterion_main!(benches);
@@ -1,6 +1,15 @@
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, FixedOffset, TimeDelta};
 use clap::{
-    command, crate_authors, crate_description, crate_name, crate_version, Parser, Subcommand,
+    crate_authors, crate_description, crate_name, crate_version, Args, Parser, Subcommand,
+    ValueEnum,
+};
+
+use crate::{
+    cli::report::{CoverageFormat, HuntFormat, PacketFormat, ReportFormat},
+    gradescope::types::parse_timestamp,
+    rufus::{parse_normalization_spec, Anonymizer, EmissionSchema, Normalization, ReviewState},
+    sources::{open_source, SourceKind, SubmissionSource},
 };
 
 #[derive(Parser)]
@@ -19,34 +28,668 @@ pub enum Command {
         #[clap(required = true)]
         #[arg(name = "export files")]
         filepaths: Vec<Utf8PathBuf>,
+
+        #[command(flatten)]
+        source: SourceArgs,
+
+        #[arg(long = "format", value_enum, default_value_t = ReportFormat::Text, help = "Output format.")]
+        format: ReportFormat,
     },
 
     #[command(about = "Detect plagiarism in the given export files")]
-    Hunt {
-        #[clap(required = true)]
-        #[arg(name = "export files")]
-        filepaths: Vec<Utf8PathBuf>,
+    Hunt(HuntArgs),
 
-        #[arg(long="group-size", short='k', default_value=None, help="Number of emissions that must match to be grouped together.")]
-        group_size: Option<usize>,
-
-        #[arg(
-            long = "show-emissions",
-            short = 'S',
-            default_value = "false",
-            help = "Show the emissions for each group in the output."
-        )]
-        show_emissions: bool,
-
-        #[arg(long = "min-size", short = 'm', default_value = "2", value_parser = clap::value_parser!(u64).range(1..), help = "Minimum number of submissions required in a group to be shown.")]
-        min_size: u64,
-
-        #[arg(
-            long = "exact",
-            short = 'E',
-            default_value = "false",
-            help = "Only show groups that match exactly on k emissions (removes k+1 group submissions from the k groups)."
-        )]
-        exact: bool,
-    },
+    #[command(about = "Show which emission IDs every submission emitted")]
+    Coverage(CoverageArgs),
+
+    #[command(about = "List submissions with missing emissions, and why")]
+    Diagnose(DiagnoseArgs),
+
+    #[command(about = "Measure how well hunt finds the planted rings in a synthetic export")]
+    Evaluate(EvaluateArgs),
+
+    #[command(about = "Write an evidence packet for one of the groups found by hunt")]
+    Packet(PacketArgs),
+
+    #[command(about = "Review the groups found by hunt interactively")]
+    Review(ReviewArgs),
+
+    #[command(about = "Map the pseudonyms in anonymized reports back to students")]
+    Deanonymize(DeanonymizeArgs),
+
+    #[command(about = "Derive per-student seeds for generating randomized problem instances")]
+    Seed(SeedArgs),
+
+    #[command(about = "Generate a synthetic Gradescope export with planted cheating rings")]
+    Synth(SynthArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SourceArgs {
+    #[arg(long = "source", short = 's', value_enum, default_value_t = SourceKind::Auto, help = "Kind of submission source the files are.")]
+    pub kind: SourceKind,
+
+    #[arg(
+        long = "stream",
+        default_value = "false",
+        help = "Parse Gradescope exports one submission at a time, keeping only what's needed for emissions (for very large exports). Every submission is still held in memory, just without the rest of its results."
+    )]
+    pub stream: bool,
+
+    #[arg(
+        long = "failed-outputs",
+        help = "Also compare the output of failed graded tests, as if each were an emission keyed by the test's number, like `test:1.1` (for assignments without emissions)."
+    )]
+    pub failed_outputs: bool,
+}
+
+impl SourceArgs {
+    pub fn open(&self, path: &Utf8Path) -> Box<dyn SubmissionSource> {
+        open_source(path, self.kind, self.stream)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct HuntArgs {
+    #[clap(required = true)]
+    #[arg(name = "export files")]
+    pub filepaths: Vec<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub grouping: GroupingArgs,
+
+    #[arg(
+        long = "show-emissions",
+        short = 'S',
+        default_value = "false",
+        help = "Show the emissions for each group in the output."
+    )]
+    pub show_emissions: bool,
+
+    #[arg(
+        long = "expected",
+        value_name = "FILE",
+        help = "CSV (sid,emission_id,value) of each student's expected output for their seed. Flags emissions that match another student's expected output."
+    )]
+    pub expected: Option<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub signing: SigningSecretArgs,
+
+    #[command(flatten)]
+    pub review: ReviewFilterArgs,
+
+    #[arg(
+        long = "code-similarity",
+        help = "Compare the submitted code of each group's members (token-normalized k-gram winnowing). Needs ZIP exports."
+    )]
+    pub code_similarity: bool,
+
+    #[arg(
+        long = "roster",
+        value_name = "FILE",
+        help = "CSV roster with a sid column and a section column, to color graph nodes by section."
+    )]
+    pub roster: Option<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub anonymize: AnonymizeArgs,
+
+    #[arg(long = "format", value_enum, default_value_t = HuntFormat::Text, help = "Output format.")]
+    pub format: HuntFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct PacketArgs {
+    #[clap(required = true)]
+    #[arg(name = "export files")]
+    pub filepaths: Vec<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub grouping: GroupingArgs,
+
+    #[arg(
+        long = "group",
+        short = 'g',
+        value_name = "NUMBER",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Number of the group, as printed by `rufus hunt` with the same options."
+    )]
+    pub group: u64,
+
+    #[arg(
+        long = "output",
+        short = 'o',
+        value_name = "DIR",
+        help = "Directory to write the packet to (created if needed)."
+    )]
+    pub output: Utf8PathBuf,
+
+    #[command(flatten)]
+    pub review: ReviewFilterArgs,
+
+    #[command(flatten)]
+    pub anonymize: AnonymizeArgs,
+
+    #[arg(long = "format", value_enum, default_value_t = PacketFormat::Markdown, help = "Format of the packet's summary.")]
+    pub format: PacketFormat,
+}
+
+/// Reviews from `rufus review` to compare groups against. `hunt` and `packet` share these so
+/// that group numbers line up.
+#[derive(Debug, Args)]
+pub struct ReviewFilterArgs {
+    #[arg(
+        long = "review-file",
+        value_name = "FILE",
+        help = "Review file saved by `rufus review`. Marks groups that were reviewed before and hides the ones that haven't changed."
+    )]
+    pub review_file: Option<Utf8PathBuf>,
+
+    #[arg(
+        long = "show-reviewed",
+        default_value = "false",
+        requires = "review_file",
+        help = "Also show groups that were already reviewed and haven't changed."
+    )]
+    pub show_reviewed: bool,
+}
+
+impl ReviewFilterArgs {
+    pub fn load(&self) -> Result<Option<ReviewState>, String> {
+        self.review_file
+            .as_ref()
+            .map(|path| {
+                ReviewState::load(path)
+                    .map_err(|e| format!("Failed to load review file {}: {}", path, e))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct CoverageArgs {
+    #[clap(required = true)]
+    #[arg(name = "export files")]
+    pub filepaths: Vec<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub anonymize: AnonymizeArgs,
+
+    #[arg(long = "format", value_enum, default_value_t = CoverageFormat::Text, help = "Output format. CSV is written to stdout, with the summary on stderr.")]
+    pub format: CoverageFormat,
+
+    #[arg(
+        long = "threshold",
+        short = 't',
+        default_value = "0.9",
+        value_parser = parse_fraction,
+        help = "Report emission IDs emitted by fewer than this fraction of submissions."
+    )]
+    pub threshold: f64,
+
+    #[arg(
+        long = "incomplete",
+        default_value = "false",
+        help = "Only show submissions missing at least one emission ID."
+    )]
+    pub incomplete: bool,
+}
+
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(x) if (0.0..=1.0).contains(&x) => Ok(x),
+        Ok(_) => Err("must be between 0 and 1".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct DiagnoseArgs {
+    #[clap(required = true)]
+    #[arg(name = "export files")]
+    pub filepaths: Vec<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub anonymize: AnonymizeArgs,
+
+    #[arg(long = "format", value_enum, default_value_t = ReportFormat::Text, help = "Output format.")]
+    pub format: ReportFormat,
+}
+
+/// Settings that decide which submissions are grouped together, shared by `hunt` and
+/// `evaluate`.
+#[derive(Debug, Args)]
+pub struct GroupingArgs {
+    #[arg(long="group-size", short='k', default_value=None, help="Number of emissions that must match to be grouped together.")]
+    pub group_size: Option<usize>,
+
+    #[arg(long = "min-size", short = 'm', default_value = "2", value_parser = clap::value_parser!(u64).range(1..), help = "Minimum number of submissions required in a group to be shown.")]
+    pub min_size: u64,
+
+    #[arg(
+        long = "exact",
+        short = 'E',
+        default_value = "false",
+        help = "Only show groups that match exactly on k emissions (removes k+1 group submissions from the k groups)."
+    )]
+    pub exact: bool,
+
+    #[arg(
+        long = "normalize",
+        short = 'n',
+        value_name = "ID=MODES",
+        value_parser = parse_normalization_spec,
+        help = "Normalize values of an emission ID before comparing (repeatable). MODES is a comma-separated list of trim, whitespace, casefold, sorted-list, numeric[:tol], matrix[:tol] (which round to the nearest multiple of tol). Use * as the ID to set the default."
+    )]
+    pub normalize: Vec<(String, Normalization)>,
+
+    #[arg(
+        long = "schema",
+        value_name = "FILE",
+        help = "YAML file giving the type (integer, float, vector, matrix, string) and tolerance of each emission ID."
+    )]
+    pub schema: Option<Utf8PathBuf>,
+
+    #[arg(
+        long = "max-p-value",
+        value_name = "P",
+        value_parser = parse_fraction,
+        help = "Only show groups whose estimated chance of matching by coincidence is at most P."
+    )]
+    pub max_p_value: Option<f64>,
+
+    #[arg(long = "sort-by", value_enum, default_value_t = SortBy::Size, help = "How to order the groups (ties keep the largest groups first).")]
+    pub sort_by: SortBy,
+
+    #[arg(
+        long = "top",
+        value_name = "N",
+        help = "Only show the first N groups, after sorting."
+    )]
+    pub top: Option<usize>,
+
+    #[arg(
+        long = "within",
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Only show groups whose members all submitted within DURATION of each other (e.g. 30m, 2h, 1h30m, 1d). Groups with an untimestamped member (e.g. from CSV or log sources) are excluded."
+    )]
+    pub within: Option<TimeDelta>,
+
+    #[arg(
+        long = "after",
+        value_name = "TIME",
+        value_parser = parse_time,
+        help = "Only show groups whose members all submitted at or after TIME (e.g. \"2024-09-09 23:00 -04:00\" or RFC 3339). Groups with an untimestamped member (e.g. from CSV or log sources) are excluded."
+    )]
+    pub after: Option<DateTime<FixedOffset>>,
+
+    #[arg(
+        long = "before",
+        value_name = "TIME",
+        value_parser = parse_time,
+        help = "Only show groups whose members all submitted at or before TIME. Groups with an untimestamped member (e.g. from CSV or log sources) are excluded."
+    )]
+    pub before: Option<DateTime<FixedOffset>>,
+
+    #[arg(
+        long = "exclude-full-marks",
+        help = "Hide groups whose members all passed the parent tests of the matched emissions, since correct answers are expected to match. Groups whose emissions have no parent tests are kept."
+    )]
+    pub exclude_full_marks: bool,
+}
+
+/// Parses a duration like `45s`, `30m`, `2h`, `1d`, or a combination like `1h30m`.
+fn parse_duration(s: &str) -> Result<TimeDelta, String> {
+    let invalid = || format!("invalid duration \"{}\" (expected e.g. 30m, 2h, 1h30m)", s);
+    let mut total = TimeDelta::zero();
+    let mut number = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+        let delta = match c {
+            's' => TimeDelta::try_seconds(n),
+            'm' => TimeDelta::try_minutes(n),
+            'h' => TimeDelta::try_hours(n),
+            'd' => TimeDelta::try_days(n),
+            _ => return Err(invalid()),
+        };
+        // Durations too long to represent are rejected rather than panicking
+        total = delta
+            .and_then(|delta| total.checked_add(&delta))
+            .ok_or_else(invalid)?;
+    }
+    // A trailing number without a unit is ambiguous
+    if !number.is_empty() || s.trim().is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Parses a time given as RFC 3339, like Gradescope's `created_at`, or without seconds.
+fn parse_time(s: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .or_else(|| parse_timestamp(s))
+        .or_else(|| DateTime::parse_from_str(s, "%Y-%m-%d %H:%M %:z").ok())
+        .ok_or(format!(
+            "invalid time \"{}\" (expected e.g. \"2024-09-09 23:00 -04:00\" or 2024-09-09T23:00:00-04:00)",
+            s
+        ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    /// Largest groups first
+    Size,
+    /// Groups whose members share the most emission IDs (including ones beyond k) first
+    MatchedIds,
+    /// Least likely to be a coincidence first
+    #[value(alias = "rarity")]
+    PValue,
+    /// Groups with the earliest submission first
+    Earliest,
+    /// Groups with the latest submission first
+    Latest,
+    /// Groups whose members submitted closest together in time first
+    Spread,
+    /// Alphabetically by member name
+    Name,
+}
+
+impl GroupingArgs {
+    pub fn load_schema(&self) -> Result<EmissionSchema, String> {
+        match &self.schema {
+            Some(path) => EmissionSchema::load(path)
+                .map_err(|e| format!("Failed to load schema {}: {}", path, e)),
+            None => Ok(EmissionSchema::default()),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct EvaluateArgs {
+    #[clap(required = true)]
+    #[arg(name = "export files")]
+    pub filepaths: Vec<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[arg(
+        long = "truth",
+        value_name = "FILE",
+        required = true,
+        help = "Planted rings to evaluate against, as written by `rufus synth`."
+    )]
+    pub truth: Utf8PathBuf,
+
+    #[command(flatten)]
+    pub grouping: GroupingArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ReviewArgs {
+    #[arg(
+        name = "hunt results",
+        help = "JSON results of `rufus hunt --format json`."
+    )]
+    pub results: Utf8PathBuf,
+
+    #[arg(
+        long = "review-file",
+        value_name = "FILE",
+        help = "Where decisions are saved (defaults to <hunt results>.review.yml)."
+    )]
+    pub review_file: Option<Utf8PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct SeedArgs {
+    #[arg(
+        name = "roster",
+        help = "CSV roster with a sid column (and optional name, email, section columns)."
+    )]
+    pub roster: Utf8PathBuf,
+
+    #[arg(
+        long = "assignment",
+        short = 'a',
+        help = "Assignment name, so each assignment gets different seeds."
+    )]
+    pub assignment: String,
+
+    #[command(flatten)]
+    pub secret: SecretArgs,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct SecretArgs {
+    #[arg(long = "secret", help = "Course secret used to derive the seeds.")]
+    pub secret: Option<String>,
+
+    #[arg(
+        long = "secret-file",
+        value_name = "FILE",
+        help = "File containing the course secret."
+    )]
+    pub secret_file: Option<Utf8PathBuf>,
+}
+
+impl SecretArgs {
+    pub fn read(&self) -> Result<Vec<u8>, String> {
+        read_secret(&self.secret, &self.secret_file)?.ok_or("No secret given".to_string())
+    }
+}
+
+#[derive(Debug, Args)]
+#[group(required = false, multiple = false)]
+pub struct SigningSecretArgs {
+    #[arg(
+        long = "signing-secret",
+        help = "Course secret used to sign emissions. Reports unsigned, tampered, and replayed emissions."
+    )]
+    pub signing_secret: Option<String>,
+
+    #[arg(
+        long = "signing-secret-file",
+        value_name = "FILE",
+        help = "File containing the course secret used to sign emissions."
+    )]
+    pub signing_secret_file: Option<Utf8PathBuf>,
+}
+
+impl SigningSecretArgs {
+    pub fn read(&self) -> Result<Option<Vec<u8>>, String> {
+        read_secret(&self.signing_secret, &self.signing_secret_file)
+    }
+}
+
+#[derive(Debug, Args)]
+#[group(required = false, multiple = false)]
+pub struct AnonymizeArgs {
+    #[arg(
+        long = "anonymize",
+        value_name = "KEY",
+        help = "Replace every student's name, SID, and email with a pseudonym derived from KEY. Use `rufus deanonymize` with the same key to map them back."
+    )]
+    pub anonymize: Option<String>,
+
+    #[arg(
+        long = "anonymize-file",
+        value_name = "FILE",
+        help = "Like --anonymize, with the key read from FILE."
+    )]
+    pub anonymize_file: Option<Utf8PathBuf>,
+}
+
+impl AnonymizeArgs {
+    /// Whether a key was given, so output should be anonymized.
+    pub fn is_set(&self) -> bool {
+        self.anonymize.is_some() || self.anonymize_file.is_some()
+    }
+
+    pub fn read(&self) -> Result<Option<Anonymizer>, String> {
+        read_secret(&self.anonymize, &self.anonymize_file)
+            .map(|key| key.map(|key| Anonymizer::new(&key)))
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct DeanonymizeArgs {
+    #[clap(required = true)]
+    #[arg(name = "export files")]
+    pub filepaths: Vec<Utf8PathBuf>,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub key: AnonymizeKeyArgs,
+
+    #[arg(
+        long = "pseudonym",
+        short = 'p',
+        value_name = "PSEUDONYM",
+        help = "Pseudonym to look up (repeatable). Lists every student's pseudonym if not given."
+    )]
+    pub pseudonyms: Vec<String>,
+
+    #[arg(long = "format", value_enum, default_value_t = ReportFormat::Text, help = "Output format.")]
+    pub format: ReportFormat,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct AnonymizeKeyArgs {
+    #[arg(long = "key", help = "Key the report was anonymized with.")]
+    pub key: Option<String>,
+
+    #[arg(
+        long = "key-file",
+        value_name = "FILE",
+        help = "File containing the key the report was anonymized with."
+    )]
+    pub key_file: Option<Utf8PathBuf>,
+}
+
+impl AnonymizeKeyArgs {
+    pub fn read(&self) -> Result<Anonymizer, String> {
+        read_secret(&self.key, &self.key_file)?
+            .map(|key| Anonymizer::new(&key))
+            .ok_or("No key given".to_string())
+    }
+}
+
+/// Reads a secret given either directly or as a file (ignoring a trailing newline).
+fn read_secret(
+    secret: &Option<String>,
+    secret_file: &Option<Utf8PathBuf>,
+) -> Result<Option<Vec<u8>>, String> {
+    match (secret, secret_file) {
+        (Some(secret), _) => Ok(Some(secret.as_bytes().to_vec())),
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map(|s| Some(s.trim_end_matches(['\r', '\n']).as_bytes().to_vec()))
+            .map_err(|e| format!("Failed to read secret file {}: {}", path, e)),
+        (None, None) => Ok(None),
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SynthArgs {
+    #[arg(
+        name = "output file",
+        help = "Where to write the synthetic submission_metadata.yml."
+    )]
+    pub output: Utf8PathBuf,
+
+    #[arg(
+        long = "truth",
+        value_name = "FILE",
+        help = "Where to write the planted rings (defaults to <output>.truth.yml)."
+    )]
+    pub truth: Option<Utf8PathBuf>,
+
+    #[arg(
+        long = "submissions",
+        short = 'n',
+        default_value = "200",
+        help = "Number of submissions."
+    )]
+    pub submissions: usize,
+
+    #[arg(
+        long = "ids",
+        default_value = "6",
+        help = "Number of emission IDs per submission."
+    )]
+    pub ids: usize,
+
+    #[arg(
+        long = "collision-rate",
+        default_value = "0.05",
+        help = "Chance that an honest emission collides with a common value."
+    )]
+    pub collision_rate: f64,
+
+    #[arg(
+        long = "ring",
+        value_name = "SIZE",
+        default_values_t = [3, 2, 2],
+        help = "Size of a planted cheating ring (repeatable)."
+    )]
+    pub rings: Vec<usize>,
+
+    #[arg(
+        long = "shared-ids",
+        default_value = "4",
+        help = "Number of emission IDs the members of each ring share."
+    )]
+    pub shared_ids: usize,
+
+    #[arg(long = "seed", default_value = "0", help = "Seed for the generator.")]
+    pub seed: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_and_time() {
+        assert_eq!(parse_duration("30m"), Ok(TimeDelta::minutes(30)));
+        assert_eq!(parse_duration("1h30m"), Ok(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("1d"), Ok(TimeDelta::hours(24)));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("9999999999999d").is_err());
+        assert!(parse_duration("106751991167d106751991167d").is_err());
+
+        let expected = parse_time("2024-09-09T23:00:00-04:00").unwrap();
+        assert_eq!(parse_time("2024-09-09 23:00 -04:00"), Ok(expected));
+        assert_eq!(
+            parse_time("2024-09-10 03:00:00.000000000 +00:00"),
+            Ok(expected)
+        );
+        assert!(parse_time("2024-09-09 23:00").is_err());
+    }
 }
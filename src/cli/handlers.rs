@@ -1,124 +1,773 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
 use camino::Utf8PathBuf;
 use itertools::Itertools;
-use std::collections::BTreeSet;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use colored::Colorize;
 
 use crate::{
-    cli::utils::print_group,
+    cli::{
+        clap::{
+            CoverageArgs, DeanonymizeArgs, DiagnoseArgs, EvaluateArgs, GroupingArgs, HuntArgs,
+            PacketArgs, ReviewArgs, ReviewFilterArgs, SeedArgs, SortBy, SourceArgs, SynthArgs,
+        },
+        packet,
+        report::{
+            print_json, AuthenticityReport, CountReport, CoverageFormat, DiagnoseReport, FileCount,
+            ForeignSeedReport, GroupReport, HuntFormat, HuntReport, PseudonymReport, ReportFormat,
+            ReviewReport,
+        },
+        review::{self, ReviewApp},
+        similarity::compare_code,
+        utils::{
+            print_authenticity_findings, print_coverage, print_diagnoses, print_evaluation,
+            print_foreign_seed_matches, print_group, print_low_coverage, print_pseudonyms,
+        },
+    },
     gradescope::{
-        loaders::{load_export, load_exports},
-        types::{LatestSubmission, SubmissionTrait},
+        archive::is_zip,
+        types::{SubmissionTrait, Submitter},
     },
-    rufus::{EmissionsGroup, Grouping},
+    rufus::{
+        derive_seeds, diagnose, hunt, verify_emissions, Anonymizer, CollusionGraph, Coverage,
+        CoverageRow, Diagnosis, EmissionSchema, EmissionsGroup, ExpectedOutputs, Grouping,
+        NormalizationConfig, ReviewState, ReviewStatus, Roster,
+    },
+    sources::{AnonymizedSubmission, BoxedSubmission, FailedOutputSubmission},
+    synth::{generate, write_export, Evaluation, GroundTruth, SynthConfig},
 };
 
-pub fn handle_count(filepaths: &Vec<Utf8PathBuf>) {
-    match load_exports(filepaths) {
-        Ok(exports) => {
-            let count = exports.iter().map(|e| e.len()).sum::<usize>();
-            println!("Total submissions: {}", count);
+pub fn handle_count(filepaths: &[Utf8PathBuf], source: &SourceArgs, format: ReportFormat) {
+    // Load the sources in parallel (errors propagate up)
+    match filepaths
+        .par_iter()
+        .map(|fp| source.open(fp).load())
+        .collect::<Result<Vec<_>, String>>()
+    {
+        Ok(sources) => {
+            let count = sources.iter().map(|s| s.len()).sum::<usize>();
+            match format {
+                ReportFormat::Text => println!("Total submissions: {}", count),
+                ReportFormat::Json => print_json(&CountReport {
+                    files: filepaths
+                        .iter()
+                        .zip(&sources)
+                        .map(|(fp, s)| FileCount {
+                            path: fp.to_string(),
+                            submissions: s.len(),
+                        })
+                        .collect(),
+                    total: count,
+                }),
+            }
         }
         Err(e) => eprintln!("{}", e),
     }
 }
 
-pub fn handle_hunt(
-    filepaths: &Vec<Utf8PathBuf>,
-    group_size: &Option<usize>,
-    show_emissions: &bool,
-    min_size: &usize,
-    exact: &bool,
-) {
-    // Grab submissions from loaded exports
-    let submissions: Vec<LatestSubmission> = filepaths
+/// Loads the submissions from every file, reporting progress (if `verbose`) and skipping any
+/// that fail. With `--failed-outputs`, failed tests' output is added to their emissions.
+fn load_submissions(
+    filepaths: &[Utf8PathBuf],
+    source: &SourceArgs,
+    verbose: bool,
+) -> Vec<BoxedSubmission> {
+    let submissions: Vec<BoxedSubmission> = filepaths
         .iter()
         .flat_map(|fp| {
-            print!("Parsing file {}... ", fp);
-            match load_export(fp) {
-                Ok(export) => {
-                    println!("{}", "DONE".green());
-                    Some(export)
+            let source = source.open(fp);
+            if verbose {
+                print!("Parsing file {}... ", source.describe());
+            }
+            match source.load() {
+                Ok(submissions) => {
+                    if verbose {
+                        println!("{}", "DONE".green());
+                    }
+                    submissions
                 }
-                Err(_) => {
-                    println!("{}", "FAILED".red());
-                    None
+                Err(e) => {
+                    if verbose {
+                        println!("{} ({})", "FAILED".red(), e);
+                    } else {
+                        eprintln!("Failed to parse file {}: {}", source.describe(), e);
+                    }
+                    vec![]
                 }
             }
         })
-        .flat_map(|e| e.into_values())
         .collect();
-    println!();
+    if verbose {
+        println!();
+    }
+    if source.failed_outputs {
+        submissions
+            .into_iter()
+            .map(|s| Box::new(FailedOutputSubmission::new(s)) as BoxedSubmission)
+            .collect()
+    } else {
+        submissions
+    }
+}
+
+/// Replaces every submitter with their pseudonym, if anonymizing.
+fn anonymize(
+    submissions: Vec<BoxedSubmission>,
+    anonymizer: Option<&Anonymizer>,
+) -> Vec<BoxedSubmission> {
+    match anonymizer {
+        Some(anonymizer) => submissions
+            .into_iter()
+            .map(|s| Box::new(AnonymizedSubmission::new(s, anonymizer)) as BoxedSubmission)
+            .collect(),
+        None => submissions,
+    }
+}
 
-    // Parse emissions from the submissions
+/// Parses the emissions of every submission, typing and normalizing their values for
+/// comparison.
+fn parse_emissions<'a>(
+    submissions: &'a [BoxedSubmission],
+    schema: &EmissionSchema,
+    normalization: &NormalizationConfig,
+    verbose: bool,
+) -> Vec<EmissionsGroup<'a>> {
     let emissions = submissions
         .iter()
-        .map(|s| s.parse_emissions())
+        .map(|s| {
+            let mut group = EmissionsGroup::from_submission(s.as_ref());
+            group.apply_schema(schema);
+            group.normalize(normalization);
+            group
+        })
         .collect::<Vec<EmissionsGroup>>();
 
-    let total_emissions = emissions.iter().map(|e| e.len()).sum::<usize>();
-    println!(
-        "Parsed {} total emissions over {} submissions.\n",
-        total_emissions.to_string().bold(),
-        submissions.len().to_string().underline()
-    );
+    if verbose {
+        let total_emissions = emissions.iter().map(|e| e.len()).sum::<usize>();
+        println!(
+            "Parsed {} total emissions over {} submissions.\n",
+            total_emissions.to_string().bold(),
+            submissions.len().to_string().underline()
+        );
 
-    // Hunt for groups of submissions with k identical emissions
-    let k = group_size.unwrap_or_else(|| emissions.iter().map(|e| e.len()).max().unwrap_or(0));
-    print!(
-        "Finding groups of emissions (k = {} | exact = {} | min_size = {})... ",
-        k.to_string().blue(),
-        exact.to_string().blue(),
-        min_size.to_string().blue()
-    );
+        let without_emissions = emissions.iter().filter(|e| e.is_empty()).count();
+        if without_emissions > 0 {
+            println!(
+                "{} submissions produced no emissions and can't be grouped (run `rufus diagnose` to see why).\n",
+                without_emissions.to_string().yellow()
+            );
+        }
+    }
+    emissions
+}
+
+/// The number of emissions that must match: `-k` if given, otherwise every emission of the
+/// submission with the most.
+fn group_size(emissions: &[EmissionsGroup], args: &GroupingArgs) -> usize {
+    args.group_size
+        .unwrap_or_else(|| emissions.iter().map(|e| e.len()).max().unwrap_or(0))
+}
+
+/// Orders groups by `--sort-by`, keeping the given order for ties.
+/// Groups missing what they're sorted by (e.g. timestamps) go last.
+fn sort_groups<'a>(mut groups: Vec<Grouping<'a>>, args: &GroupingArgs) -> Vec<Grouping<'a>> {
+    match args.sort_by {
+        SortBy::Size => {}
+        SortBy::MatchedIds => groups.sort_by_cached_key(|g| Reverse(g.matched_ids().len())),
+        SortBy::PValue => groups.sort_by(|a, b| a.p_value().total_cmp(&b.p_value())),
+        SortBy::Earliest => groups.sort_by_cached_key(|g| (g.earliest().is_none(), g.earliest())),
+        SortBy::Latest => groups.sort_by_cached_key(|g| Reverse(g.latest())),
+        SortBy::Spread => {
+            groups.sort_by_cached_key(|g| (g.time_spread().is_none(), g.time_spread()))
+        }
+        SortBy::Name => groups.sort_by_cached_key(|g| {
+            g.groups()
+                .iter()
+                .flat_map(|m| m.submission().submitters())
+                .map(|s| s.name.clone())
+                .min()
+        }),
+    }
+    groups
+}
 
-    let groups = hunt(&emissions, k, *exact)
+/// Keeps the first `--top` groups, if it was given.
+fn top<T>(mut groups: Vec<T>, args: &GroupingArgs) -> Vec<T> {
+    if let Some(top) = args.top {
+        groups.truncate(top);
+    }
+    groups
+}
+
+/// Hunts for groups of submissions with k identical emissions, ordered by `--sort-by`. `--top`
+/// isn't applied here, since hunt hides reviewed groups first.
+fn find_groups<'a>(
+    emissions: &'a [EmissionsGroup<'a>],
+    args: &GroupingArgs,
+    verbose: bool,
+) -> Vec<Grouping<'a>> {
+    let min_size = args.min_size as usize;
+    let k = group_size(emissions, args);
+    if verbose {
+        print!(
+            "Finding groups of emissions (k = {} | exact = {} | min_size = {})... ",
+            k.to_string().blue(),
+            args.exact.to_string().blue(),
+            min_size.to_string().blue()
+        );
+    }
+
+    let groups = hunt(emissions, k, args.exact)
         .into_iter()
-        .filter(|g| g.len() >= *min_size)
+        .filter(|g| g.len() >= min_size)
+        .filter(|g| args.max_p_value.is_none_or(|max| g.p_value() <= max))
+        .filter(|g| {
+            args.within
+                .is_none_or(|within| g.time_spread().is_some_and(|s| s <= within))
+        })
+        .filter(|g| {
+            (args.after.is_none() && args.before.is_none())
+                || g.submitted_between(args.after, args.before)
+        })
+        .filter(|g| !(args.exclude_full_marks && g.all_full_marks()))
         .sorted_by_key(|g| g.len())
         .rev()
         .collect::<Vec<_>>();
-    println!("found {} groups.\n", groups.len().to_string().underline());
+    let groups = sort_groups(groups, args);
+    if verbose {
+        println!("found {} groups.\n", groups.len().to_string().underline());
+    }
+    groups
+}
+
+/// The groups `hunt` shows, in the order they're numbered in, with how each compares to
+/// earlier reviews, and how many reviewed groups were hidden. `packet` selects groups the
+/// same way, so its `--group` numbers are the ones `hunt` printed.
+fn select_groups<'a, 's>(
+    emissions: &'a [EmissionsGroup<'a>],
+    args: &GroupingArgs,
+    review: &ReviewFilterArgs,
+    review_state: Option<&'s ReviewState>,
+    verbose: bool,
+) -> (Vec<(Grouping<'a>, Option<ReviewStatus<'s>>)>, usize) {
+    // Compare against earlier reviews, hiding the groups that were already reviewed
+    let (shown, hidden): (Vec<_>, Vec<_>) = find_groups(emissions, args, verbose)
+        .into_iter()
+        .map(|g| {
+            let status = review_state.map(|s| s.status(&g.identity()));
+            (g, status)
+        })
+        .partition(|(_, status)| {
+            review.show_reviewed || !matches!(status, Some(ReviewStatus::Reviewed(_)))
+        });
+    // --top counts the groups that are shown, so hidden ones don't use up the limit
+    (top(shown, args), hidden.len())
+}
+
+pub fn handle_hunt(args: &HuntArgs) {
+    let anonymizer = match args.anonymize.read() {
+        Ok(anonymizer) => anonymizer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let normalization = NormalizationConfig::new(&args.grouping.normalize);
+    let schema = match args.grouping.load_schema() {
+        Ok(schema) => schema,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let expected = match &args.expected {
+        Some(path) => match ExpectedOutputs::load(path) {
+            Ok(mut expected) => {
+                expected.normalize(&schema, &normalization);
+                if let Some(anonymizer) = &anonymizer {
+                    expected.anonymize(anonymizer);
+                }
+                expected
+            }
+            Err(e) => {
+                eprintln!("Failed to load expected outputs {}: {}", path, e);
+                return;
+            }
+        },
+        None => ExpectedOutputs::default(),
+    };
+    let signing_secret = match args.signing.read() {
+        Ok(secret) => secret,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let review_state = match args.review.load() {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    // Sections to color the graph by, keyed like the (possibly anonymized) submitters
+    let sections: HashMap<String, String> = match &args.roster {
+        Some(path) => match Roster::load(path) {
+            Ok(roster) => roster
+                .entries()
+                .iter()
+                .filter_map(|entry| {
+                    let key = match &anonymizer {
+                        Some(anonymizer) => anonymizer.pseudonym_for_key(&entry.sid),
+                        None => entry.sid.clone(),
+                    };
+                    Some((key, entry.section.clone()?))
+                })
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to load roster {}: {}", path, e);
+                return;
+            }
+        },
+        None => HashMap::new(),
+    };
+
+    // Grab submissions from the loaded sources
+    let verbose = args.format == HuntFormat::Text;
+    let submissions = anonymize(
+        load_submissions(&args.filepaths, &args.source, verbose),
+        anonymizer.as_ref(),
+    );
+    let emissions = parse_emissions(&submissions, &schema, &normalization, verbose);
+    let (groups, hidden) = select_groups(
+        &emissions,
+        &args.grouping,
+        &args.review,
+        review_state.as_ref(),
+        verbose,
+    );
+    let shown: Vec<&Grouping> = groups.iter().map(|(g, _)| g).collect();
+
+    // Confirm with the members' submitted code, if asked
+    let code_similarity = if args.code_similarity {
+        if !args.filepaths.iter().any(is_zip) {
+            eprintln!("--code-similarity needs ZIP exports to read submitted code from");
+        }
+        match compare_code(
+            &shown,
+            &args.filepaths,
+            args.source.stream,
+            anonymizer.as_ref(),
+        ) {
+            Ok(similarity) => similarity,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    } else {
+        vec![vec![]; groups.len()]
+    };
+
+    // Check for emissions that belong to another student's seed
+    let foreign_matches = (!expected.is_empty()).then(|| expected.find_foreign_matches(&emissions));
+
+    // Check that emissions were really printed by the autograder
+    let findings = signing_secret
+        .as_ref()
+        .map(|secret| verify_emissions(&emissions, secret));
 
     // PRINTING
-    for (i, grouping) in groups.iter().enumerate() {
-        print_group(i + 1, grouping, *show_emissions);
+    match args.format {
+        HuntFormat::Text => {
+            for (i, ((grouping, status), code)) in groups.iter().zip(&code_similarity).enumerate() {
+                print_group(i + 1, grouping, args.show_emissions, status.as_ref(), code);
+            }
+            if hidden > 0 {
+                println!(
+                    "Hid {} previously reviewed groups (use --show-reviewed to show them).\n",
+                    hidden.to_string().bold()
+                );
+            }
+            if let Some(matches) = &foreign_matches {
+                print_foreign_seed_matches(matches);
+            }
+            if let Some(findings) = &findings {
+                print_authenticity_findings(findings, &emissions);
+            }
+        }
+        HuntFormat::Dot => print!("{}", CollusionGraph::new(&shown, &sections).to_dot()),
+        HuntFormat::Graphml => print!("{}", CollusionGraph::new(&shown, &sections).to_graphml()),
+        HuntFormat::Json => print_json(&HuntReport {
+            submissions: submissions.len(),
+            emissions: emissions.iter().map(|e| e.len()).sum(),
+            k: group_size(&emissions, &args.grouping),
+            exact: args.grouping.exact,
+            min_size: args.grouping.min_size as usize,
+            groups: groups
+                .iter()
+                .zip(code_similarity)
+                .map(|((grouping, status), code_similarity)| GroupReport {
+                    review: status.as_ref().map(ReviewReport::from),
+                    code_similarity,
+                    ..GroupReport::from(grouping)
+                })
+                .collect(),
+            hidden_reviewed: hidden,
+            foreign_seed_matches: foreign_matches
+                .map(|matches| matches.iter().map(ForeignSeedReport::from).collect()),
+            authenticity_findings: findings.map(|findings| {
+                findings
+                    .iter()
+                    .map(|f| AuthenticityReport::new(f, &emissions))
+                    .collect()
+            }),
+        }),
     }
 }
 
-pub fn hunt<'a>(groups: &'a [EmissionsGroup<'a>], k: usize, exact: bool) -> Vec<Grouping<'a>> {
-    let all_emission_ids: BTreeSet<&String> =
-        groups.iter().flat_map(|g| g.emission_ids()).collect();
+pub fn handle_coverage(args: &CoverageArgs) {
+    let anonymizer = match args.anonymize.read() {
+        Ok(anonymizer) => anonymizer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let verbose = args.format == CoverageFormat::Text;
+    let submissions = anonymize(
+        load_submissions(&args.filepaths, &args.source, verbose),
+        anonymizer.as_ref(),
+    );
+    let refs: Vec<&dyn SubmissionTrait> = submissions
+        .iter()
+        .map(|s| s.as_ref() as &dyn SubmissionTrait)
+        .collect();
+    let mut coverage = Coverage::new(&refs);
+    coverage.rows.sort_by(|a, b| {
+        let name = |r: &CoverageRow| r.submission.submitters().first().map(|s| s.name.clone());
+        name(a).cmp(&name(b))
+    });
+    // Summarize before hiding complete rows
+    let below = coverage.below(args.threshold);
+    if args.incomplete {
+        coverage.rows.retain(|r| !r.is_complete());
+    }
 
-    let mut groupings: Vec<Grouping> = vec![];
-    for on_ids in all_emission_ids
+    match args.format {
+        CoverageFormat::Text => {
+            print_coverage(&coverage);
+            print_low_coverage(&below, submissions.len(), args.threshold);
+        }
+        CoverageFormat::Csv => {
+            if let Err(e) = write_coverage_csv(&coverage) {
+                eprintln!("{}", e);
+            }
+            // Keep stdout to the CSV
+            for (id, count) in &below {
+                eprintln!(
+                    "\"{}\" emitted by {} of {} submissions",
+                    id,
+                    count,
+                    submissions.len()
+                );
+            }
+        }
+    }
+}
+
+fn write_coverage_csv(coverage: &Coverage) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    let header = ["name", "sid", "email"]
         .into_iter()
-        .combinations(k)
-        .map(|ids| ids.into_iter().collect::<BTreeSet<&String>>())
+        .chain(coverage.ids.iter().map(|id| id.as_str()));
+    writer.write_record(header)?;
+    for row in &coverage.rows {
+        let submitters = row.submission.submitters();
+        let join = |f: &dyn Fn(&Submitter) -> String| submitters.iter().map(f).join("; ");
+        let record = [
+            join(&|s| s.name.clone()),
+            join(&|s| s.sid.clone().unwrap_or_default()),
+            join(&|s| s.email.clone()),
+        ]
+        .into_iter()
+        .chain(row.cells.iter().map(|c| c.name().to_string()));
+        writer.write_record(record)?;
+    }
+    writer.flush().map_err(csv::Error::from)
+}
+
+pub fn handle_diagnose(args: &DiagnoseArgs) {
+    let anonymizer = match args.anonymize.read() {
+        Ok(anonymizer) => anonymizer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let verbose = args.format == ReportFormat::Text;
+    let submissions = anonymize(
+        load_submissions(&args.filepaths, &args.source, verbose),
+        anonymizer.as_ref(),
+    );
+    let refs: Vec<&dyn SubmissionTrait> = submissions
+        .iter()
+        .map(|s| s.as_ref() as &dyn SubmissionTrait)
+        .collect();
+    let diagnoses = diagnose(&refs)
+        .into_iter()
+        .sorted_by(|a, b| {
+            let name = |d: &Diagnosis| d.submission.submitters().first().map(|s| s.name.clone());
+            name(a).cmp(&name(b))
+        })
+        .collect::<Vec<_>>();
+
+    match args.format {
+        ReportFormat::Text => print_diagnoses(submissions.len(), &diagnoses),
+        ReportFormat::Json => print_json(&DiagnoseReport::new(submissions.len(), &diagnoses)),
+    }
+}
+
+pub fn handle_evaluate(args: &EvaluateArgs) {
+    let normalization = NormalizationConfig::new(&args.grouping.normalize);
+    let schema = match args.grouping.load_schema() {
+        Ok(schema) => schema,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let truth = match GroundTruth::load(&args.truth) {
+        Ok(truth) => truth,
+        Err(e) => {
+            eprintln!("Failed to load planted rings {}: {}", args.truth, e);
+            return;
+        }
+    };
+
+    let submissions = load_submissions(&args.filepaths, &args.source, true);
+    let emissions = parse_emissions(&submissions, &schema, &normalization, true);
+    let groups = top(
+        find_groups(&emissions, &args.grouping, true),
+        &args.grouping,
+    );
+
+    print_evaluation(&Evaluation::new(&truth, &groups));
+}
+
+pub fn handle_packet(args: &PacketArgs) {
+    let anonymizer = match args.anonymize.read() {
+        Ok(anonymizer) => anonymizer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let normalization = NormalizationConfig::new(&args.grouping.normalize);
+    let schema = match args.grouping.load_schema() {
+        Ok(schema) => schema,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let review_state = match args.review.load() {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    // Select groups exactly as hunt does, so the number picks the group hunt printed
+    let submissions = anonymize(
+        load_submissions(&args.filepaths, &args.source, true),
+        anonymizer.as_ref(),
+    );
+    let emissions = parse_emissions(&submissions, &schema, &normalization, true);
+    let (groups, _) = select_groups(
+        &emissions,
+        &args.grouping,
+        &args.review,
+        review_state.as_ref(),
+        true,
+    );
+    let group_num = args.group as usize;
+    let Some((grouping, _)) = groups.get(group_num - 1) else {
+        eprintln!(
+            "There is no group {} (found {} groups with these options).",
+            group_num,
+            groups.len()
+        );
+        return;
+    };
+
+    if anonymizer.is_some() && args.filepaths.iter().any(is_zip) {
+        eprintln!(
+            "The submitted files copied into the packet aren't anonymized, and can identify the students"
+        );
+    }
+    match packet::write_packet(group_num, grouping, &emissions, args, anonymizer.as_ref()) {
+        Ok(summary) => println!(
+            "Wrote the packet for group {} to {} (see {}).",
+            group_num.to_string().bold(),
+            args.output,
+            summary
+        ),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+pub fn handle_review(args: &ReviewArgs) {
+    let report: HuntReport = match std::fs::read_to_string(&args.results)
+        .map_err(|e| e.to_string())
+        .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
     {
-        let mut grouped_for_curr_ids: Vec<Grouping> = vec![];
-        for group_a in groups {
-            // TODO: don't clone here
-
-            let add_to_grouped = grouped_for_curr_ids
-                .iter_mut()
-                .find(|grouping| grouping.matches_group_on_ids(group_a, Some(&on_ids), exact));
-
-            match add_to_grouped {
-                Some(grouping) => {
-                    // If a matching group is found, add to it
-                    grouping.add_group(group_a);
-                }
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Failed to load hunt results {}: {}", args.results, e);
+            return;
+        }
+    };
+    if report.groups.is_empty() {
+        println!("No groups to review.");
+        return;
+    }
+
+    let review_file = args
+        .review_file
+        .clone()
+        .unwrap_or_else(|| args.results.with_extension("review.yml"));
+    let state = match ReviewState::load(&review_file) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Failed to load review file {}: {}", review_file, e);
+            return;
+        }
+    };
+
+    let mut app = ReviewApp::new(report, state, review_file.clone());
+    if let Err(e) = review::run(&mut app) {
+        eprintln!("Review failed: {}", e);
+        return;
+    }
+    println!(
+        "Reviewed {} of {} groups (saved to {}).",
+        app.reviewed().to_string().bold(),
+        app.groups().len(),
+        review_file
+    );
+}
+
+pub fn handle_deanonymize(args: &DeanonymizeArgs) {
+    let anonymizer = match args.key.read() {
+        Ok(anonymizer) => anonymizer,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let verbose = args.format == ReportFormat::Text;
+    let submissions = load_submissions(&args.filepaths, &args.source, verbose);
+
+    // Every student in the exports, by pseudonym
+    let students: BTreeMap<String, &Submitter> = submissions
+        .iter()
+        .flat_map(|s| s.submitters())
+        .map(|s| (anonymizer.pseudonym(s), s))
+        .collect();
+    let found: Vec<(&String, &Submitter)> = if args.pseudonyms.is_empty() {
+        students.iter().map(|(p, s)| (p, *s)).collect()
+    } else {
+        args.pseudonyms
+            .iter()
+            .filter_map(|p| match students.get_key_value(p) {
+                Some((p, s)) => Some((p, *s)),
                 None => {
-                    // Otherwise, create a new group with this submission
-                    grouped_for_curr_ids.push(Grouping::new(on_ids.clone(), vec![group_a]));
+                    eprintln!("No student in the exports has the pseudonym {}", p);
+                    None
                 }
-            }
+            })
+            .collect()
+    };
+
+    match args.format {
+        ReportFormat::Text => print_pseudonyms(&found),
+        ReportFormat::Json => print_json(
+            &found
+                .iter()
+                .map(|(p, s)| PseudonymReport::new(p, s))
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+pub fn handle_seed(args: &SeedArgs) {
+    let secret = match args.secret.read() {
+        Ok(secret) => secret,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
         }
+    };
+    let roster = match Roster::load(&args.roster) {
+        Ok(roster) => roster,
+        Err(e) => {
+            eprintln!("Failed to load roster {}: {}", args.roster, e);
+            return;
+        }
+    };
 
-        // Add the groupings for the current set of IDs to the main map
-        groupings.extend(grouped_for_curr_ids);
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    let result = writer
+        .write_record(["sid", "name", "seed", "seed_hex"])
+        .and_then(|_| {
+            derive_seeds(&secret, &roster, &args.assignment)
+                .iter()
+                .try_for_each(|seed| {
+                    writer.write_record([
+                        seed.sid.clone(),
+                        seed.name.clone().unwrap_or_default(),
+                        seed.as_u64().to_string(),
+                        seed.as_hex(),
+                    ])
+                })
+        })
+        .and_then(|_| writer.flush().map_err(csv::Error::from));
+    if let Err(e) = result {
+        eprintln!("{}", e);
     }
+}
+
+pub fn handle_synth(args: &SynthArgs) {
+    let config = SynthConfig {
+        num_submissions: args.submissions,
+        num_emission_ids: args.ids,
+        collision_rate: args.collision_rate,
+        rings: args.rings.clone(),
+        ring_shared_ids: args.shared_ids,
+        seed: args.seed,
+    };
+    let truth_path = args
+        .truth
+        .clone()
+        .unwrap_or_else(|| args.output.with_extension("truth.yml"));
 
-    return groupings;
+    let (export, truth) = generate(&config);
+    let result = write_export(&export, &args.output).and_then(|_| truth.save(&truth_path));
+    match result {
+        Ok(()) => println!(
+            "Wrote {} submissions to {} and {} planted rings to {}.",
+            export.len().to_string().bold(),
+            args.output,
+            truth.rings.len().to_string().bold(),
+            truth_path
+        ),
+        Err(e) => eprintln!("{}", e),
+    }
 }
@@ -1,3 +1,7 @@
 pub mod clap;
 pub mod handlers;
+pub mod packet;
+pub mod report;
+pub mod review;
+pub mod similarity;
 pub mod utils;
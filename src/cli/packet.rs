@@ -0,0 +1,419 @@
+use std::{collections::BTreeSet, fmt::Write, fs};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::ValueEnum;
+use itertools::Itertools;
+
+use pulldown_cmark::{html, Event, Options, Parser};
+
+use crate::{
+    cli::{
+        clap::PacketArgs,
+        report::{format_duration, format_p_value, PacketFormat},
+    },
+    gradescope::{
+        archive::{is_zip, ExportArchive},
+        types::{LatestSubmission, SubmissionTrait},
+    },
+    rufus::{Anonymizer, EmissionsGroup, Grouping},
+};
+
+/// The folder in a packet that submitted files are copied into.
+const SUBMISSIONS_DIR: &str = "submissions";
+
+/// A group member's submitted files, copied out of a ZIP export.
+pub struct CopiedSubmission {
+    /// The submission's key in the export, e.g. `submission_1234`
+    pub key: String,
+    pub submitters: String,
+    pub files: usize,
+}
+
+/// Writes an evidence packet for `grouping` to `args.output`: a summary describing the group
+/// (`summary.md`, or `summary.html` with `--format html`), and the members' submitted files
+/// for every input that's a ZIP export. Returns the summary's path.
+pub fn write_packet(
+    group_num: usize,
+    grouping: &Grouping,
+    population: &[EmissionsGroup],
+    args: &PacketArgs,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<Utf8PathBuf, String> {
+    fs::create_dir_all(&args.output)
+        .map_err(|e| format!("Failed to create {}: {}", args.output, e))?;
+
+    let mut copied = vec![];
+    for path in args.filepaths.iter().filter(|p| is_zip(p)) {
+        copied.extend(
+            copy_submissions(
+                grouping,
+                path,
+                &args.output.join(SUBMISSIONS_DIR),
+                anonymizer,
+            )
+            .map_err(|e| format!("Failed to copy submissions from {}: {}", path, e))?,
+        );
+    }
+
+    let summary = render_summary(group_num, grouping, population, args, &copied);
+    let (path, contents) = match args.format {
+        PacketFormat::Markdown => (args.output.join("summary.md"), summary),
+        PacketFormat::Html => (
+            args.output.join("summary.html"),
+            render_html(group_num, &summary),
+        ),
+    };
+    fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(path)
+}
+
+/// Who submitted a submission and when, which is enough to find it again in an export.
+pub fn submission_key(submission: &dyn SubmissionTrait) -> (Vec<String>, String) {
+    let submitters = submission
+        .submitters()
+        .iter()
+        .map(|s| s.key().to_string())
+        .sorted()
+        .collect();
+    (submitters, submission.created_at().clone())
+}
+
+/// The `submission_key` of a submission read straight from an export, as it would be after
+/// anonymizing with `anonymizer`.
+pub fn export_submission_key(
+    submission: &LatestSubmission,
+    anonymizer: Option<&Anonymizer>,
+) -> (Vec<String>, String) {
+    let submitters = submission
+        .submitters
+        .iter()
+        .map(|s| match anonymizer {
+            Some(anonymizer) => anonymizer.pseudonym(s),
+            None => s.key().to_string(),
+        })
+        .sorted()
+        .collect();
+    (submitters, submission.created_at.clone())
+}
+
+/// Copies the folders of the group's submissions in the ZIP export at `path` into `dest`.
+/// `anonymizer` must be the one the group was anonymized with, so its members can be found
+/// (and are listed by their pseudonyms).
+fn copy_submissions(
+    grouping: &Grouping,
+    path: &Utf8Path,
+    dest: &Utf8Path,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<Vec<CopiedSubmission>, String> {
+    let members: BTreeSet<_> = grouping
+        .groups()
+        .iter()
+        .map(|g| submission_key(g.submission()))
+        .collect();
+
+    let mut archive = ExportArchive::open(path)?;
+    let export = archive.load()?;
+    let mut copied = vec![];
+    for (key, submission) in export.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+        if !members.contains(&export_submission_key(submission, anonymizer)) {
+            continue;
+        }
+        copied.push(CopiedSubmission {
+            key: key.clone(),
+            submitters: submission
+                .submitters
+                .iter()
+                .map(|s| match anonymizer {
+                    Some(anonymizer) => anonymizer.pseudonym(s),
+                    None => s.name.clone(),
+                })
+                .join(", "),
+            files: archive.extract_submission(key, dest)?,
+        });
+    }
+    Ok(copied)
+}
+
+/// Renders the Markdown summary as a standalone HTML page.
+fn render_html(group_num: usize, summary: &str) -> String {
+    // Show any HTML in the summary as text, so submitted values can't inject markup
+    let parser = Parser::new_ext(summary, Options::ENABLE_TABLES).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, parser);
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Rufus Evidence Packet: Group {}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 60em; margin: 2em auto; padding: 0 1em; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }}
+pre {{ background: #f4f4f4; padding: 0.6em; overflow-x: auto; }}
+</style>
+</head>
+<body>
+{}</body>
+</html>
+",
+        group_num, body
+    )
+}
+
+/// Escapes `text` so it's shown as is, rather than adding formatting, links, or HTML (and
+/// doesn't break a Markdown table cell).
+fn cell(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' => " ".to_string(),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '&' | '|' => format!("\\{}", c),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// An inline code span long enough that `text` can't close it early.
+fn code_span(text: &str) -> String {
+    let text = text.replace('\n', " ");
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+/// A fenced code block long enough that the value can't close it early.
+fn code_block(value: &str) -> String {
+    let longest_run = value
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+    format!("{}\n{}\n{}\n", fence, value, fence)
+}
+
+fn render_summary(
+    group_num: usize,
+    grouping: &Grouping,
+    population: &[EmissionsGroup],
+    args: &PacketArgs,
+    copied: &[CopiedSubmission],
+) -> String {
+    // Writing to a String can't fail
+    let mut out = String::new();
+    let on_ids = grouping.sorted_on_ids().iter().join(", ");
+    let _ = writeln!(out, "# Rufus Evidence Packet: Group {}\n", group_num);
+    let _ = writeln!(
+        out,
+        "{} submissions emitted identical values for {} emission IDs ({}).\n",
+        grouping.len(),
+        grouping.on_ids().len(),
+        on_ids
+    );
+    let _ = writeln!(
+        out,
+        "Estimated chance of matching by coincidence: {} (from how common each value is in the class, assuming the emission IDs are independent).\n",
+        format_p_value(grouping.p_value())
+    );
+
+    if let (Some(earliest), Some(spread)) = (grouping.earliest(), grouping.time_spread()) {
+        let _ = writeln!(
+            out,
+            "The members submitted within {} of each other, starting {}.\n",
+            format_duration(spread),
+            earliest.format("%Y-%m-%d %H:%M:%S %:z")
+        );
+    }
+
+    let _ = writeln!(out, "## Students\n");
+    let _ = writeln!(out, "| Name | SID | Email | Submitted | Score |");
+    let _ = writeln!(out, "| --- | --- | --- | --- | --- |");
+    let members = grouping
+        .groups()
+        .iter()
+        .flat_map(|g| g.submission().submitters().iter().map(move |s| (s, g)))
+        .sorted_by(|a, b| a.0.name.cmp(&b.0.name));
+    for (submitter, group) in members {
+        let submission = group.submission();
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            cell(&submitter.name),
+            cell(submitter.sid.as_deref().unwrap_or("")),
+            cell(&submitter.email),
+            cell(submission.created_at()),
+            match submission.max_score() {
+                Some(max) => format!("{}/{}", submission.score(), max),
+                None => submission.score().to_string(),
+            }
+        );
+    }
+
+    let _ = writeln!(out, "\n## Matched Emissions\n");
+    let counts = grouping.value_counts(population);
+    let incorrect = grouping.shared_incorrect_ids();
+    for id in &grouping.sorted_on_ids() {
+        let Some(shared) = grouping.groups()[0].emissions_map().get(id) else {
+            continue;
+        };
+        let _ = writeln!(out, "### {}\n", code_span(id.as_str()));
+        if let Some(parent) = grouping.parent_tests(*id).into_iter().flatten().next() {
+            let _ = writeln!(
+                out,
+                "Checked by the test \"{}\"{}.\n",
+                cell(&parent.name),
+                if incorrect.contains(id) {
+                    ", which **every member got wrong**"
+                } else {
+                    ""
+                }
+            );
+        }
+        let (same, emitted) = counts.get(id).copied().unwrap_or((0, 0));
+        let _ = writeln!(
+            out,
+            "Class-wide, {} of the {} submissions that emitted {} have this value ({:.1}%).\n",
+            same,
+            emitted,
+            code_span(id.as_str()),
+            100.0 * same as f64 / emitted.max(1) as f64
+        );
+
+        // The decoded values as submitted, which can differ when they were normalized
+        let raw_values: Vec<(&str, String)> = grouping
+            .groups()
+            .iter()
+            .filter_map(|g| {
+                let name = g
+                    .submission()
+                    .submitters()
+                    .iter()
+                    .map(|s| &s.name)
+                    .join(", ");
+                Some((g.emissions_map().get(id)?.value(), name))
+            })
+            .sorted()
+            .collect();
+        if raw_values.iter().map(|(value, _)| value).all_equal() {
+            let _ = writeln!(out, "{}", code_block(shared.value()));
+        } else {
+            let _ = writeln!(
+                out,
+                "The values match after normalization:\n\n{}",
+                code_block(&match shared.typed() {
+                    Some(_) => shared.display_value(),
+                    None => shared.key().to_string(),
+                })
+            );
+            for (value, names) in raw_values {
+                let _ = writeln!(out, "{}:\n\n{}", cell(&names), code_block(value));
+            }
+        }
+    }
+
+    let _ = writeln!(out, "## Submitted Files\n");
+    if copied.is_empty() {
+        let _ = writeln!(
+            out,
+            "No submitted files are included (they're only available from ZIP exports).\n"
+        );
+    }
+    for submission in copied {
+        let _ = writeln!(
+            out,
+            "- {}: {} ({} files)",
+            cell(&submission.submitters),
+            code_span(&format!("{}/{}/", SUBMISSIONS_DIR, submission.key)),
+            submission.files
+        );
+    }
+    if !copied.is_empty() {
+        let _ = writeln!(out);
+        if args.anonymize.is_set() {
+            let _ = writeln!(
+                out,
+                "The submitted files aren't anonymized, and can identify the students.\n"
+            );
+        }
+    }
+
+    let _ = writeln!(out, "## Run Parameters\n");
+    let _ = writeln!(out, "- Rufus version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "- Export files: {}", args.filepaths.iter().join(", "));
+    let _ = writeln!(out, "- Submissions compared: {}", population.len());
+    let _ = writeln!(
+        out,
+        "- Group size (k): {}",
+        args.grouping
+            .group_size
+            .map(|k| k.to_string())
+            .unwrap_or(format!("every emission ({})", grouping.on_ids().len()))
+    );
+    let _ = writeln!(out, "- Minimum group size: {}", args.grouping.min_size);
+    if let Some(sort_by) = args.grouping.sort_by.to_possible_value() {
+        let _ = writeln!(out, "- Sorted by: {}", sort_by.get_name());
+    }
+    let _ = writeln!(out, "- Exact: {}", args.grouping.exact);
+    if let Some(schema) = &args.grouping.schema {
+        let _ = writeln!(out, "- Schema: {}", schema);
+    }
+    for (id, normalization) in &args.grouping.normalize {
+        let _ = writeln!(out, "- Normalization: `{}={}`", id, normalization);
+    }
+    if let Some(review_file) = &args.review.review_file {
+        let _ = writeln!(
+            out,
+            "- Review file: {} ({} reviewed groups)",
+            review_file,
+            if args.review.show_reviewed {
+                "showing"
+            } else {
+                "hiding"
+            }
+        );
+    }
+    if args.anonymize.is_set() {
+        let _ = writeln!(out, "- Anonymized: yes");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html_escapes_markup() {
+        let summary = format!(
+            "<script>alert(1)</script>\n\nBy <b>inline</b> and {}: {}\n\n| Name |\n| --- |\n| {} |\n",
+            cell("[link](javascript:alert(1))"),
+            code_span("`</code><img src=x>"),
+            cell("Eve | <img src=x onerror=alert(1)>")
+        );
+        let html = render_html(1, &summary);
+
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<b>"));
+        assert!(!html.contains("<img"));
+        assert!(!html.contains("<a "));
+        assert!(html.contains("<td>Eve | &lt;img src=x onerror=alert(1)&gt;</td>"));
+    }
+
+    #[test]
+    fn test_code_span_fits_backticks() {
+        assert_eq!(code_span("x"), "`x`");
+        assert_eq!(code_span("a`b"), "``a`b``");
+        assert_eq!(code_span("`a\nb"), "`` `a b ``");
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::BTreeMap;
+
+use chrono::TimeDelta;
+use clap::ValueEnum;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gradescope::types::{Score, Submitter},
+    rufus::{
+        AuthenticityFinding, AuthenticityIssue, Decision, Diagnosis, EmissionIssue, EmissionsGroup,
+        ForeignSeedMatch, GroupIdentity, Grouping, ReviewStatus,
+    },
+};
+
+/// How a subcommand prints its results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Human-readable, colored output
+    Text,
+    /// A single JSON document on stdout, with progress and errors on stderr
+    Json,
+}
+
+/// How `hunt` prints its results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HuntFormat {
+    /// Human-readable, colored output
+    Text,
+    /// A single JSON document on stdout, with progress and errors on stderr
+    Json,
+    /// The network of who matched whom, as a Graphviz DOT graph
+    Dot,
+    /// The network of who matched whom, as GraphML (e.g. for Gephi)
+    Graphml,
+}
+
+/// What `packet` writes its summary as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PacketFormat {
+    /// `summary.md`
+    Markdown,
+    /// `summary.html`, a standalone page that opens in any browser
+    Html,
+}
+
+/// How `coverage` prints its matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CoverageFormat {
+    /// A colored grid
+    Text,
+    /// One row per submission, one column per emission ID
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CountReport {
+    pub files: Vec<FileCount>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileCount {
+    pub path: String,
+    pub submissions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HuntReport {
+    pub submissions: usize,
+    pub emissions: usize,
+    pub k: usize,
+    pub exact: bool,
+    pub min_size: usize,
+    pub groups: Vec<GroupReport>,
+
+    /// Groups left out because they were already reviewed
+    #[serde(default)]
+    pub hidden_reviewed: usize,
+
+    /// Only present when `--expected` was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreign_seed_matches: Option<Vec<ForeignSeedReport>>,
+
+    /// Only present when a signing secret was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authenticity_findings: Option<Vec<AuthenticityReport>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemberReport {
+    pub name: String,
+    pub sid: Option<String>,
+    pub email: String,
+
+    /// Every emission of the member's submission (raw values), for group members
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub emissions: BTreeMap<String, String>,
+
+    /// The member's score, for group members
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
+
+    /// The most points the member could have gotten, if the results say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_score: Option<Score>,
+}
+
+impl MemberReport {
+    /// A key identifying the student, like `Submitter::key`.
+    pub fn key(&self) -> &str {
+        match &self.sid {
+            Some(sid) => sid,
+            None if !self.email.is_empty() => &self.email,
+            None => &self.name,
+        }
+    }
+}
+
+impl From<&Submitter> for MemberReport {
+    fn from(submitter: &Submitter) -> Self {
+        MemberReport {
+            name: submitter.name.clone(),
+            sid: submitter.sid.clone(),
+            email: submitter.email.clone(),
+            emissions: BTreeMap::new(),
+            score: None,
+            max_score: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupReport {
+    pub on_ids: Vec<String>,
+    pub members: Vec<MemberReport>,
+    pub emissions: Vec<MatchedEmissionReport>,
+
+    /// The estimated chance the members match by coincidence (missing from older reports)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p_value: Option<f64>,
+
+    /// The spread of the members' scores
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scores: Option<ScoreReport>,
+
+    /// How similar each pair of members' submitted code is, when `--code-similarity` was given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub code_similarity: Vec<CodeSimilarityReport>,
+
+    /// When the members submitted, if their submissions have timestamps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<TimingReport>,
+
+    /// How the group compares to earlier reviews, when a review file was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<ReviewReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreReport {
+    pub min: Score,
+    pub max: Score,
+    pub mean: Score,
+    /// Every member passed the matched emissions' parent tests, so their values are probably
+    /// just correct
+    pub all_full_marks: bool,
+    /// The `[score, max]` every member got on the matched emissions' parent tests, when it's
+    /// the same for everyone, short of full marks, and they emitted the same wrong values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identical_partial: Option<(Score, Score)>,
+}
+
+impl ScoreReport {
+    pub fn new(grouping: &Grouping) -> Option<Self> {
+        let scores = grouping.scores();
+        let (min, max) = scores.iter().copied().minmax().into_option()?;
+        Some(ScoreReport {
+            min,
+            max,
+            mean: scores.iter().sum::<Score>() / scores.len() as Score,
+            all_full_marks: grouping.all_full_marks(),
+            identical_partial: grouping.identical_partial_scores(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeSimilarityReport {
+    /// The submitters of one submission
+    pub first: String,
+    /// The submitters of the other
+    pub second: String,
+    /// The fraction of the smaller submission's code fingerprints found in the other
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimingReport {
+    /// RFC 3339
+    pub earliest: String,
+    /// RFC 3339
+    pub latest: String,
+    /// The time between the earliest and latest submissions
+    pub spread_seconds: i64,
+}
+
+impl TimingReport {
+    pub fn new(grouping: &Grouping) -> Option<Self> {
+        Some(TimingReport {
+            earliest: grouping.earliest()?.to_rfc3339(),
+            latest: grouping.latest()?.to_rfc3339(),
+            spread_seconds: grouping.time_spread()?.num_seconds(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewReport {
+    /// `new`, `reviewed`, `grown`, or `changed`
+    pub status: String,
+    /// The earlier decision, if there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<Decision>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    /// Members that joined since the group was reviewed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_members: Vec<String>,
+}
+
+impl From<&ReviewStatus<'_>> for ReviewReport {
+    fn from(status: &ReviewStatus) -> Self {
+        ReviewReport {
+            status: status.name().to_string(),
+            decision: status.previous().map(|p| p.decision),
+            note: status
+                .previous()
+                .map(|p| p.note.clone())
+                .unwrap_or_default(),
+            added_members: match status {
+                ReviewStatus::Grown { added, .. } => added.clone(),
+                _ => vec![],
+            },
+        }
+    }
+}
+
+/// The value a group matched on for one emission ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchedEmissionReport {
+    pub id: String,
+    /// The (normalized) value every member shares
+    pub value: String,
+    /// The distinct raw values behind `value`, if normalization made them equal
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub raw_values: Vec<String>,
+    /// The name of the graded test this emission belongs to, if Rufus could tell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_test: Option<String>,
+    /// Every member failed or lost points on the parent test
+    #[serde(default)]
+    pub shared_incorrect: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignSeedReport {
+    pub submitters: Vec<MemberReport>,
+    pub emission_id: String,
+    pub matches_sids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthenticityReport {
+    pub submitters: Vec<MemberReport>,
+    pub emission_id: String,
+    /// `forged`, `tampered`, or `replayed`
+    pub issue: String,
+    /// For replays, the other submitters that used the same nonce
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replayed_by: Vec<MemberReport>,
+}
+
+fn members(submitters: &[Submitter]) -> Vec<MemberReport> {
+    submitters.iter().map(MemberReport::from).collect()
+}
+
+impl From<&Grouping<'_>> for GroupReport {
+    fn from(grouping: &Grouping) -> Self {
+        let incorrect = grouping.shared_incorrect_ids();
+        let emissions = grouping
+            .sorted_on_ids()
+            .iter()
+            .filter_map(|id| {
+                let shared = grouping.groups().first()?.emissions_map().get(id)?;
+                let raw_values: Vec<String> = grouping
+                    .groups()
+                    .iter()
+                    .filter_map(|g| g.emissions_map().get(id))
+                    .map(|e| e.value().to_string())
+                    .unique()
+                    .collect();
+                Some(MatchedEmissionReport {
+                    id: id.to_string(),
+                    value: shared.key().to_string(),
+                    raw_values: if raw_values.len() > 1 {
+                        raw_values
+                    } else {
+                        vec![]
+                    },
+                    parent_test: grouping
+                        .parent_tests(*id)
+                        .into_iter()
+                        .flatten()
+                        .map(|t| t.name.clone())
+                        .next(),
+                    shared_incorrect: incorrect.contains(id),
+                })
+            })
+            .collect();
+
+        GroupReport {
+            on_ids: grouping
+                .sorted_on_ids()
+                .iter()
+                .map(|id| id.to_string())
+                .collect(),
+            members: grouping
+                .groups()
+                .iter()
+                .flat_map(|g| {
+                    let emissions: BTreeMap<String, String> = g
+                        .emissions()
+                        .map(|e| (e.id().to_string(), e.value().to_string()))
+                        .collect();
+                    let submission = g.submission();
+                    submission.submitters().iter().map(move |s| MemberReport {
+                        emissions: emissions.clone(),
+                        score: Some(*submission.score()),
+                        max_score: submission.max_score(),
+                        ..MemberReport::from(s)
+                    })
+                })
+                .sorted_by(|a, b| a.name.cmp(&b.name))
+                .collect(),
+            emissions,
+            p_value: Some(grouping.p_value()),
+            scores: ScoreReport::new(grouping),
+            code_similarity: vec![],
+            timing: TimingReport::new(grouping),
+            review: None,
+        }
+    }
+}
+
+impl GroupReport {
+    pub fn identity(&self) -> GroupIdentity {
+        GroupIdentity::new(
+            self.members.iter().map(|m| m.key().to_string()),
+            self.emissions
+                .iter()
+                .map(|e| (e.id.clone(), e.value.clone())),
+        )
+    }
+}
+
+impl From<&ForeignSeedMatch<'_>> for ForeignSeedReport {
+    fn from(m: &ForeignSeedMatch) -> Self {
+        ForeignSeedReport {
+            submitters: members(m.group.submission().submitters()),
+            emission_id: m.emission_id.to_string(),
+            matches_sids: m.matches_sids.iter().cloned().collect(),
+        }
+    }
+}
+
+impl AuthenticityReport {
+    pub fn new(finding: &AuthenticityFinding, groups: &[EmissionsGroup]) -> Self {
+        let (issue, replayed_by) = match &finding.issue {
+            AuthenticityIssue::Forged => ("forged", vec![]),
+            AuthenticityIssue::Tampered => ("tampered", vec![]),
+            AuthenticityIssue::Replayed { other_groups } => (
+                "replayed",
+                other_groups
+                    .iter()
+                    .flat_map(|i| members(groups[*i].submission().submitters()))
+                    .collect(),
+            ),
+        };
+        AuthenticityReport {
+            submitters: members(finding.group.submission().submitters()),
+            emission_id: finding.emission_id.to_string(),
+            issue: issue.to_string(),
+            replayed_by,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnoseReport {
+    pub submissions: usize,
+    /// The number of submissions with each kind of issue
+    pub issue_counts: BTreeMap<String, usize>,
+    pub diagnoses: Vec<DiagnosisReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosisReport {
+    pub submitters: Vec<MemberReport>,
+    pub emissions: usize,
+    pub issues: Vec<IssueReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssueReport {
+    pub category: String,
+    pub message: String,
+    /// The autograder's output, the parse errors, or the missing IDs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+impl From<&EmissionIssue> for IssueReport {
+    fn from(issue: &EmissionIssue) -> Self {
+        IssueReport {
+            category: issue.category().to_string(),
+            message: issue.to_string(),
+            details: issue_details(issue),
+        }
+    }
+}
+
+/// The specifics behind an issue, one per line.
+pub fn issue_details(issue: &EmissionIssue) -> Vec<String> {
+    match issue {
+        EmissionIssue::AutograderFailure { output } => output
+            .iter()
+            .flat_map(|o| o.lines())
+            .map(|l| l.to_string())
+            .collect(),
+        EmissionIssue::ParseErrors(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        EmissionIssue::MissingIds(ids) => ids.iter().map(|id| id.to_string()).sorted().collect(),
+        EmissionIssue::Unprocessed { .. } | EmissionIssue::MissingEmissionTests => vec![],
+    }
+}
+
+impl DiagnoseReport {
+    pub fn new(submissions: usize, diagnoses: &[Diagnosis]) -> Self {
+        let mut issue_counts = BTreeMap::new();
+        for issue in diagnoses.iter().flat_map(|d| &d.issues) {
+            *issue_counts
+                .entry(issue.category().to_string())
+                .or_insert(0) += 1;
+        }
+        DiagnoseReport {
+            submissions,
+            issue_counts,
+            diagnoses: diagnoses
+                .iter()
+                .map(|d| DiagnosisReport {
+                    submitters: members(d.submission.submitters()),
+                    emissions: d.emissions,
+                    issues: d.issues.iter().map(IssueReport::from).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PseudonymReport {
+    pub pseudonym: String,
+    pub name: String,
+    pub sid: Option<String>,
+    pub email: String,
+}
+
+impl PseudonymReport {
+    pub fn new(pseudonym: &str, submitter: &Submitter) -> Self {
+        PseudonymReport {
+            pseudonym: pseudonym.to_string(),
+            name: submitter.name.clone(),
+            sid: submitter.sid.clone(),
+            email: submitter.email.clone(),
+        }
+    }
+}
+
+/// Formats a p-value for people: fixed-point while it's readable, scientific once it's tiny.
+pub fn format_p_value(p: f64) -> String {
+    if p >= 0.001 {
+        format!("{:.3}", p)
+    } else {
+        format!("{:.1e}", p)
+    }
+}
+
+/// Formats a duration with its two largest units, e.g. `29m 23s` or `1d 4h`.
+pub fn format_duration(duration: TimeDelta) -> String {
+    let seconds = duration.num_seconds().abs();
+    let parts = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let shown: Vec<String> = parts
+        .iter()
+        .skip_while(|(n, _)| *n == 0)
+        .take(2)
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect();
+    if shown.is_empty() {
+        "0s".to_string()
+    } else {
+        shown.join(" ")
+    }
+}
+
+/// Prints a report as pretty JSON on stdout.
+pub fn print_json<T: Serialize>(report: &T) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to write JSON: {}", e),
+    }
+}
@@ -0,0 +1,611 @@
+use std::io;
+
+use camino::Utf8PathBuf;
+use chrono::TimeDelta;
+use itertools::Itertools;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Wrap},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    cli::report::{format_duration, format_p_value, GroupReport, HuntReport, MemberReport},
+    rufus::{Decision, GroupIdentity, ReviewState},
+};
+
+/// Collapsed values are cut to this many characters.
+const COLLAPSED_WIDTH: usize = 60;
+
+/// How many lines PgUp/PgDn scroll the details view by.
+const SCROLL_LINES: u16 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Details,
+    Diff,
+}
+
+/// The state of the `review` TUI: the groups from a hunt, the decisions made about them, and
+/// what's on screen. Every decision is saved to the review file as soon as it's made.
+pub struct ReviewApp {
+    groups: Vec<GroupReport>,
+    identities: Vec<GroupIdentity>,
+    state: ReviewState,
+    review_file: Utf8PathBuf,
+    selected: usize,
+    expanded: bool,
+    view: View,
+    /// Which pair of the selected group's members the diff view compares
+    pair: usize,
+    /// How many lines the details view is scrolled down
+    scroll: u16,
+    /// The note being typed, if one is being edited
+    note: Option<String>,
+    status: String,
+    quit: bool,
+}
+
+impl ReviewApp {
+    pub fn new(report: HuntReport, state: ReviewState, review_file: Utf8PathBuf) -> Self {
+        let identities = report.groups.iter().map(|g| g.identity()).collect();
+        ReviewApp {
+            groups: report.groups,
+            identities,
+            state,
+            review_file,
+            selected: 0,
+            expanded: false,
+            view: View::Details,
+            pair: 0,
+            scroll: 0,
+            note: None,
+            status: String::new(),
+            quit: false,
+        }
+    }
+
+    pub fn state(&self) -> &ReviewState {
+        &self.state
+    }
+
+    pub fn groups(&self) -> &Vec<GroupReport> {
+        &self.groups
+    }
+
+    /// The number of groups with a decision.
+    pub fn reviewed(&self) -> usize {
+        self.identities
+            .iter()
+            .filter(|id| self.state.get(id).is_some())
+            .count()
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if self.note.is_some() {
+            self.handle_note_key(key);
+            return;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Enter | KeyCode::Char('e') => self.expanded = !self.expanded,
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(SCROLL_LINES),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(SCROLL_LINES),
+            KeyCode::Char('d') => {
+                self.view = match self.view {
+                    View::Details => View::Diff,
+                    View::Diff => View::Details,
+                }
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                let pairs = self.pairs().len();
+                if pairs > 0 {
+                    self.pair = (self.pair + 1) % pairs;
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                let pairs = self.pairs().len();
+                if pairs > 0 {
+                    self.pair = (self.pair + pairs - 1) % pairs;
+                }
+            }
+            KeyCode::Char('c') => self.decide(Some(Decision::Confirmed)),
+            KeyCode::Char('x') => self.decide(Some(Decision::Dismissed)),
+            KeyCode::Char('f') => self.decide(Some(Decision::NeedsFollowUp)),
+            KeyCode::Char('u') => self.decide(None),
+            KeyCode::Char('n') => match self.current().and_then(|id| self.state.get(id)) {
+                Some(review) => self.note = Some(review.note.clone()),
+                None => self.status = "Mark the group before adding a note.".to_string(),
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_note_key(&mut self, key: KeyCode) {
+        let Some(note) = self.note.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Enter => {
+                let note = self.note.take().unwrap_or_default();
+                if let Some(id) = self.identities.get(self.selected) {
+                    self.state.set_note(id, &note);
+                    self.save();
+                }
+            }
+            KeyCode::Esc => self.note = None,
+            KeyCode::Backspace => {
+                note.pop();
+            }
+            KeyCode::Char(c) => note.push(c),
+            _ => {}
+        }
+    }
+
+    fn current(&self) -> Option<&GroupIdentity> {
+        self.identities.get(self.selected)
+    }
+
+    fn select(&mut self, index: usize) {
+        if !self.groups.is_empty() {
+            self.selected = index.min(self.groups.len() - 1);
+            self.pair = 0;
+            self.scroll = 0;
+        }
+    }
+
+    fn decide(&mut self, decision: Option<Decision>) {
+        let Some(id) = self.identities.get(self.selected) else {
+            return;
+        };
+        match decision {
+            Some(decision) => self.state.decide(id, decision),
+            None => self.state.clear(id),
+        }
+        self.save();
+    }
+
+    fn save(&mut self) {
+        self.status = match self.state.save(&self.review_file) {
+            Ok(()) => format!("Saved to {}", self.review_file),
+            Err(e) => format!("Failed to save {}: {}", self.review_file, e),
+        };
+    }
+
+    /// Every pair of members of the selected group, by index.
+    fn pairs(&self) -> Vec<(usize, usize)> {
+        let members = self
+            .groups
+            .get(self.selected)
+            .map_or(0, |g| g.members.len());
+        (0..members).tuple_combinations().collect()
+    }
+
+    fn decision_marker(&self, index: usize) -> Span<'static> {
+        match self.state.get(&self.identities[index]).map(|r| r.decision) {
+            Some(Decision::Confirmed) => "✓".green().bold(),
+            Some(Decision::Dismissed) => "✗".dark_gray(),
+            Some(Decision::NeedsFollowUp) => "?".yellow().bold(),
+            None => " ".into(),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(frame.area());
+        let [list, detail] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        let items: Vec<ListItem> = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(i, g)| {
+                ListItem::new(Line::from(vec![
+                    self.decision_marker(i),
+                    format!(" {:>3}. ", i + 1).into(),
+                    format!("{} members", g.members.len()).bold(),
+                    format!(" on {}", g.on_ids.join(", ")).into(),
+                ]))
+            })
+            .collect();
+        let title = format!(
+            " Groups ({}/{} reviewed) ",
+            self.reviewed(),
+            self.groups.len()
+        );
+        let mut list_state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list,
+            &mut list_state,
+        );
+
+        if let Some(group) = self.groups.get(self.selected) {
+            match self.view {
+                View::Details => self.draw_details(frame, detail, group),
+                View::Diff => self.draw_diff(frame, detail, group),
+            }
+        }
+
+        let help = "↑/↓ select  PgUp/PgDn scroll  e expand  d diff  ←/→ pair  c confirm  x dismiss  f follow-up  u clear  n note  q quit";
+        let bottom = match &self.note {
+            Some(note) => Line::from(vec!["Note: ".bold(), note.clone().into(), "▏".into()]),
+            None => Line::from(self.status.clone().dim()),
+        };
+        frame.render_widget(
+            Paragraph::new(Text::from(vec![Line::from(help), bottom]))
+                .block(Block::default().borders(Borders::TOP)),
+            footer,
+        );
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: ratatui::layout::Rect, group: &GroupReport) {
+        let mut lines: Vec<Line> = vec![];
+        match self.state.get(&self.identities[self.selected]) {
+            Some(review) => {
+                lines.push(Line::from(vec![
+                    "Decision: ".bold(),
+                    review.decision.to_string().into(),
+                ]));
+                if !review.note.is_empty() {
+                    lines.push(Line::from(vec![
+                        "Note: ".bold(),
+                        review.note.clone().into(),
+                    ]));
+                }
+            }
+            None => lines.push(Line::from("Not reviewed".dim())),
+        }
+
+        if let Some(p_value) = group.p_value {
+            lines.push(Line::from(vec![
+                "Chance of coincidence: ".bold(),
+                format!("≈ {}", format_p_value(p_value)).into(),
+            ]));
+        }
+        if let Some(timing) = &group.timing {
+            lines.push(Line::from(vec![
+                "Submitted within: ".bold(),
+                format_duration(TimeDelta::seconds(timing.spread_seconds)).into(),
+                format!(" ({} to {})", timing.earliest, timing.latest).dim(),
+            ]));
+        }
+        if let Some((score, max)) = group.scores.as_ref().and_then(|s| s.identical_partial) {
+            lines.push(Line::from(
+                format!(
+                    "Identical partial scores on the matched tests ({}/{}) with identical wrong values",
+                    score, max
+                )
+                .yellow()
+                .bold(),
+            ));
+        }
+
+        for pair in &group.code_similarity {
+            lines.push(Line::from(vec![
+                "Code similarity: ".bold(),
+                format!("{:.0}%", 100.0 * pair.similarity).into(),
+                format!(" ({} / {})", pair.first, pair.second).dim(),
+            ]));
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::from("Members".underlined()));
+        for member in &group.members {
+            let mut line = Line::from(format!(
+                "  {} (SID: {})",
+                member.name,
+                member.sid.as_deref().unwrap_or("Unknown SID")
+            ));
+            if let Some(score) = member.score {
+                let score = match member.max_score {
+                    Some(max) => format!(" [score {}/{}]", score, max),
+                    None => format!(" [score {}]", score),
+                };
+                line.push_span(score.dim());
+            }
+            lines.push(line);
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::from("Matched emissions".underlined()));
+        for emission in &group.emissions {
+            let mut heading = Line::from(format!("  \"{}\"", emission.id).italic());
+            if let Some(parent) = &emission.parent_test {
+                heading.push_span(format!(" (from {})", parent).dim());
+            }
+            if emission.shared_incorrect {
+                heading.push_span(" incorrect for every member".red());
+            }
+            lines.push(heading);
+            for line in shown_value(&emission.value, self.expanded) {
+                lines.push(Line::from(format!("    {}", line).blue()));
+            }
+            if self.expanded && !emission.raw_values.is_empty() {
+                lines.push(Line::from("    Raw values:".dim()));
+                for raw in &emission.raw_values {
+                    for line in raw.lines() {
+                        lines.push(Line::from(format!("      {}", line).dim()));
+                    }
+                }
+            }
+        }
+
+        // Wrapping can only add lines, so this keeps at least the last line on screen
+        let scroll = self.scroll.min(lines.len().saturating_sub(1) as u16);
+        let title = format!(" Group {} ", self.selected + 1);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0)),
+            area,
+        );
+    }
+
+    fn draw_diff(&self, frame: &mut Frame, area: ratatui::layout::Rect, group: &GroupReport) {
+        let pairs = self.pairs();
+        let Some(&(a, b)) = pairs.get(self.pair) else {
+            frame.render_widget(
+                Paragraph::new("Nothing to compare").block(Block::default().borders(Borders::ALL)),
+                area,
+            );
+            return;
+        };
+        let (left, right) = (&group.members[a], &group.members[b]);
+
+        let ids = left
+            .emissions
+            .keys()
+            .chain(right.emissions.keys())
+            .unique()
+            .sorted();
+        let rows: Vec<Row> = ids
+            .map(|id| {
+                let (l, r) = (left.emissions.get(id), right.emissions.get(id));
+                let style = match (l, r) {
+                    (Some(l), Some(r)) if l == r => Style::default().fg(Color::Red),
+                    (Some(_), Some(_)) => Style::default(),
+                    _ => Style::default().fg(Color::DarkGray),
+                };
+                let cell = |value: Option<&String>| {
+                    let lines =
+                        value.map_or(vec!["—".to_string()], |v| shown_value(v, self.expanded));
+                    (lines.len(), Cell::from(Text::from(lines.join("\n"))))
+                };
+                let ((l_height, l_cell), (r_height, r_cell)) = (cell(l), cell(r));
+                Row::new(vec![Cell::from(id.clone()), l_cell, r_cell])
+                    .style(style)
+                    .height(l_height.max(r_height) as u16)
+            })
+            .collect();
+
+        let title = format!(
+            " Diff {}/{}: {} vs {} (shared values in red) ",
+            self.pair + 1,
+            pairs.len(),
+            member_label(left),
+            member_label(right)
+        );
+        frame.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Percentage(20),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(40),
+                ],
+            )
+            .header(
+                Row::new(vec![
+                    "ID".to_string(),
+                    member_label(left),
+                    member_label(right),
+                ])
+                .bold(),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+}
+
+fn member_label(member: &MemberReport) -> String {
+    match &member.sid {
+        Some(sid) => format!("{} ({})", member.name, sid),
+        None => member.name.clone(),
+    }
+}
+
+/// The lines of a value to show: all of them when expanded, otherwise the first line cut
+/// to `COLLAPSED_WIDTH`.
+fn shown_value(value: &str, expanded: bool) -> Vec<String> {
+    if expanded {
+        return value.lines().map(|l| l.to_string()).collect();
+    }
+    let first = value.lines().next().unwrap_or("");
+    let truncated = first.chars().count() > COLLAPSED_WIDTH || value.lines().count() > 1;
+    let shown: String = first.chars().take(COLLAPSED_WIDTH).collect();
+    vec![if truncated {
+        format!("{}…", shown)
+    } else {
+        shown
+    }]
+}
+
+/// Runs the review TUI until the reviewer quits.
+pub fn run(app: &mut ReviewApp) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_loop(&mut terminal, app);
+    ratatui::restore();
+    result
+}
+
+fn run_loop(terminal: &mut DefaultTerminal, app: &mut ReviewApp) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key.code);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::report::MatchedEmissionReport;
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::collections::BTreeMap;
+
+    fn member(name: &str, sid: &str, values: &[(&str, &str)]) -> MemberReport {
+        MemberReport {
+            name: name.to_string(),
+            sid: Some(sid.to_string()),
+            email: String::new(),
+            emissions: values
+                .iter()
+                .map(|(id, v)| (id.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            score: None,
+            max_score: None,
+        }
+    }
+
+    fn report() -> HuntReport {
+        let group = |members: Vec<MemberReport>| GroupReport {
+            on_ids: vec!["x".to_string()],
+            members,
+            emissions: vec![MatchedEmissionReport {
+                id: "x".to_string(),
+                value: "1".to_string(),
+                raw_values: vec![],
+                parent_test: None,
+                shared_incorrect: false,
+            }],
+            p_value: None,
+            timing: None,
+            scores: None,
+            code_similarity: vec![],
+            review: None,
+        };
+        HuntReport {
+            submissions: 5,
+            emissions: 10,
+            k: 1,
+            exact: false,
+            min_size: 2,
+            groups: vec![
+                group(vec![
+                    member("A", "1", &[("x", "1"), ("y", "2")]),
+                    member("B", "2", &[("x", "1"), ("y", "3")]),
+                    member("C", "3", &[("x", "1")]),
+                ]),
+                group(vec![
+                    member("D", "4", &[("x", "1")]),
+                    member("E", "5", &[("x", "1")]),
+                ]),
+            ],
+            hidden_reviewed: 0,
+            foreign_seed_matches: None,
+            authenticity_findings: None,
+        }
+    }
+
+    #[test]
+    fn test_review_keys_update_and_save_decisions() {
+        let path = std::env::temp_dir().join(format!("rufus-review-{}.yml", std::process::id()));
+        let path = Utf8PathBuf::from_path_buf(path).unwrap();
+        let mut app = ReviewApp::new(report(), ReviewState::default(), path.clone());
+
+        // Notes need a decision first
+        app.handle_key(KeyCode::Char('n'));
+        assert!(app.note.is_none());
+
+        app.handle_key(KeyCode::Char('c'));
+        app.handle_key(KeyCode::Char('n'));
+        for c in "same x".chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+        app.handle_key(KeyCode::Enter);
+        app.handle_key(KeyCode::Down);
+        app.handle_key(KeyCode::Char('x'));
+        assert_eq!(app.reviewed(), 2);
+
+        // Saved as soon as they're made
+        let saved = ReviewState::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let first = saved.get(&app.groups()[0].identity()).unwrap();
+        assert_eq!(first.decision, Decision::Confirmed);
+        assert_eq!(first.note, "same x");
+        assert_eq!(
+            saved.get(&app.groups()[1].identity()).unwrap().decision,
+            Decision::Dismissed
+        );
+
+        // Pairs wrap around
+        app.handle_key(KeyCode::Up);
+        app.handle_key(KeyCode::Left);
+        assert_eq!(app.pair, 2);
+        app.handle_key(KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_review_scrolls_details() {
+        let mut app = ReviewApp::new(report(), ReviewState::default(), "unused.yml".into());
+        let mut terminal = Terminal::new(TestBackend::new(120, 10)).unwrap();
+        let mut screen = |app: &ReviewApp| -> String {
+            terminal.draw(|frame| app.draw(frame)).unwrap();
+            terminal
+                .backend()
+                .buffer()
+                .content()
+                .iter()
+                .map(|c| c.symbol())
+                .collect()
+        };
+
+        let top = screen(&app);
+        app.handle_key(KeyCode::PageDown);
+        assert_eq!(app.scroll, SCROLL_LINES);
+        assert_ne!(screen(&app), top);
+        app.handle_key(KeyCode::PageUp);
+        assert_eq!(screen(&app), top);
+
+        // Selecting another group starts at its top
+        app.handle_key(KeyCode::PageDown);
+        app.handle_key(KeyCode::Down);
+        assert_eq!(app.scroll, 0);
+    }
+
+    #[test]
+    fn test_review_draws_diff() {
+        let mut app = ReviewApp::new(report(), ReviewState::default(), "unused.yml".into());
+        app.handle_key(KeyCode::Char('d'));
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains("Diff 1/3: A (1) vs B (2)"));
+        assert!(screen.contains("Groups (0/2 reviewed)"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use camino::Utf8PathBuf;
+use itertools::Itertools;
+
+use crate::{
+    cli::{
+        packet::{export_submission_key, submission_key},
+        report::CodeSimilarityReport,
+    },
+    gradescope::{
+        archive::{is_zip, ExportArchive},
+        types::LatestSubmission,
+    },
+    rufus::{Anonymizer, Fingerprint, Grouping},
+};
+
+/// Compares the submitted code of every pair of members in each group, using the files in the
+/// ZIP exports among `filepaths`. Only the members of `groups` are fingerprinted. Members whose
+/// files couldn't be found (e.g. the export was a bare `submission_metadata.yml`) are left
+/// out. `anonymizer` must be the one the groups were anonymized with, so members can be found.
+pub fn compare_code(
+    groups: &[&Grouping],
+    filepaths: &[Utf8PathBuf],
+    stream: bool,
+    anonymizer: Option<&Anonymizer>,
+) -> Result<Vec<Vec<CodeSimilarityReport>>, String> {
+    let members: BTreeSet<_> = groups
+        .iter()
+        .flat_map(|g| g.groups())
+        .map(|g| submission_key(g.submission()))
+        .collect();
+
+    let mut fingerprints = BTreeMap::new();
+    for path in filepaths.iter().filter(|p| is_zip(p)) {
+        let mut archive =
+            ExportArchive::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        // Only the members' folder keys are kept, so streaming never holds the whole export
+        let member_key = |(key, submission): (String, LatestSubmission)| {
+            let member = export_submission_key(&submission, anonymizer);
+            members.contains(&member).then_some((key, member))
+        };
+        let keys: Vec<(String, (Vec<String>, String))> = if stream {
+            archive.stream().and_then(|export| {
+                export
+                    .filter_map_ok(member_key)
+                    .collect::<Result<_, String>>()
+            })
+        } else {
+            archive
+                .load()
+                .map(|export| export.into_iter().filter_map(member_key).collect())
+        }
+        .map_err(|e| format!("Failed to load {}: {}", path, e))?;
+
+        for (key, member) in keys {
+            if fingerprints.contains_key(&member) {
+                continue;
+            }
+            let mut fingerprint = Fingerprint::default();
+            for (_, contents) in archive
+                .read_submission(&key)
+                .map_err(|e| format!("Failed to read {} from {}: {}", key, path, e))?
+            {
+                fingerprint.extend(Fingerprint::new(&contents));
+            }
+            fingerprints.insert(member, fingerprint);
+        }
+    }
+
+    Ok(groups
+        .iter()
+        .map(|grouping| {
+            grouping
+                .groups()
+                .iter()
+                .filter_map(|g| {
+                    let fingerprint = fingerprints.get(&submission_key(g.submission()))?;
+                    let names = g
+                        .submission()
+                        .submitters()
+                        .iter()
+                        .map(|s| &s.name)
+                        .join(", ");
+                    Some((names, fingerprint))
+                })
+                .sorted_by(|a, b| a.0.cmp(&b.0))
+                .tuple_combinations()
+                .map(|((first, a), (second, b))| CodeSimilarityReport {
+                    first,
+                    second,
+                    similarity: a.similarity(b),
+                })
+                .sorted_by(|a, b| b.similarity.total_cmp(&a.similarity))
+                .collect()
+        })
+        .collect())
+}
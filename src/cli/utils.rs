@@ -1,37 +1,402 @@
-use crate::rufus::Grouping;
+use crate::{
+    cli::report::{
+        format_duration, format_p_value, issue_details, CodeSimilarityReport, ScoreReport,
+    },
+    gradescope::types::Submitter,
+    rufus::{
+        AuthenticityFinding, AuthenticityIssue, Coverage, CoverageCell, Diagnosis, EmissionsGroup,
+        ForeignSeedMatch, Grouping, ReviewEntry, ReviewStatus, Symbol,
+    },
+    synth::Evaluation,
+};
+use chrono::TimeDelta;
 use colored::Colorize;
 use itertools::Itertools;
 
-pub fn print_group(group_num: usize, grouping: &Grouping, show_emissions: bool) {
+/// Groups whose members all submitted within this long of each other are highlighted.
+const CLUSTERED_WITHIN: TimeDelta = TimeDelta::hours(1);
+
+/// Pairs of members whose code is at least this similar are highlighted.
+const SIMILAR_CODE: f64 = 0.5;
+
+pub fn print_group(
+    group_num: usize,
+    grouping: &Grouping,
+    show_emissions: bool,
+    review: Option<&ReviewStatus>,
+    code_similarity: &[CodeSimilarityReport],
+) {
     let submitters: Vec<_> = grouping
         .groups()
         .iter()
-        .flat_map(|g| g.submission().submitters())
-        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .flat_map(|g| {
+            g.submission()
+                .submitters()
+                .iter()
+                .map(|s| (s, g.submission()))
+        })
+        .sorted_by(|a, b| a.0.name.cmp(&b.0.name))
         .collect();
 
-    println!("{}", format!("Group {}:", group_num).bold());
-    for (i, submitter) in submitters.iter().enumerate() {
-        // Print the submitter's name and ID
+    println!(
+        "{} {}",
+        format!("Group {}:", group_num).bold(),
+        format!(
+            "(chance of coincidence ≈ {})",
+            format_p_value(grouping.p_value())
+        )
+        .dimmed()
+    );
+    if let (Some(earliest), Some(latest), Some(spread)) = (
+        grouping.earliest(),
+        grouping.latest(),
+        grouping.time_spread(),
+    ) {
+        let timing = format!(
+            "Submitted within {} of each other ({} to {})",
+            format_duration(spread),
+            earliest.format("%Y-%m-%d %H:%M:%S %:z"),
+            latest.format("%Y-%m-%d %H:%M:%S %:z")
+        );
+        // Submissions this close together stand out
+        if spread <= CLUSTERED_WITHIN {
+            println!("\t{}", timing.yellow());
+        } else {
+            println!("\t{}", timing.dimmed());
+        }
+    }
+    if let Some(review) = review {
+        print_review_status(review);
+    }
+    if let Some((score, max)) = grouping.identical_partial_scores() {
+        println!(
+            "\t{}",
+            format!(
+                "Identical partial scores on the matched tests ({}/{}) with identical wrong values",
+                score, max
+            )
+            .yellow()
+            .bold()
+        );
+    } else if let Some(scores) = ScoreReport::new(grouping) {
+        println!(
+            "\t{}",
+            format!(
+                "Scores from {} to {} (mean {:.1})",
+                scores.min, scores.max, scores.mean
+            )
+            .dimmed()
+        );
+    }
+    for pair in code_similarity {
+        let line = format!(
+            "Code similarity {:.0}% ({} / {})",
+            100.0 * pair.similarity,
+            pair.first,
+            pair.second
+        );
+        if pair.similarity >= SIMILAR_CODE {
+            println!("\t{}", line.yellow().bold());
+        } else {
+            println!("\t{}", line.dimmed());
+        }
+    }
+    let incorrect = grouping.shared_incorrect_ids();
+    if !incorrect.is_empty() {
+        println!(
+            "\t{}",
+            format!(
+                "Identical incorrect output for {}",
+                incorrect.iter().map(|id| format!("\"{}\"", id)).join(", ")
+            )
+            .red()
+            .bold()
+        );
+    }
+    for (i, (submitter, submission)) in submitters.iter().enumerate() {
+        // Print the submitter's name, ID, and score
+        let score = match submission.max_score() {
+            Some(max) => format!("{}/{}", submission.score(), max),
+            None => submission.score().to_string(),
+        };
         println!(
-            "\t{} {} (SID: {})",
+            "\t{} {} (SID: {}) {}",
             format!("({})", i + 1).bold(),
             submitter.name,
-            submitter.sid.as_ref().unwrap_or(&"Unknown SID".to_string())
+            submitter.sid.as_ref().unwrap_or(&"Unknown SID".to_string()),
+            format!("[score {}]", score).dimmed()
         );
     }
 
     // Print emissions that matched the grouping criteria
     if show_emissions {
-        print!("\n\t{}", format!("Emissions:").underline());
+        print!("\n\t{}", "Emissions:".underline());
         let first = grouping.groups().first().unwrap();
-        for id in grouping.on_ids() {
-            let some_emission = first.emissions_map().get(*id).unwrap();
+        for id in &grouping.sorted_on_ids() {
+            let some_emission = first.emissions_map().get(id).unwrap();
 
             println!();
-            println!("\t\"{}\"", some_emission.id().italic());
-            println!("\t{}", some_emission.value().replace('\n', "\n\t").blue());
+            print!("\t\"{}\"", some_emission.id().as_str().italic());
+            if let Some(parent) = grouping.parent_tests(*id).into_iter().flatten().next() {
+                print!(" {}", format!("(from {})", parent.name).dimmed());
+            }
+            if incorrect.contains(id) {
+                print!(" {}", "incorrect for every member".red());
+            }
+            println!();
+            let shown = match some_emission.typed() {
+                Some(typed) => typed.to_string(),
+                None => some_emission.key().to_string(),
+            };
+            println!("\t{}", shown.replace('\n', "\n\t").blue());
+
+            // When values were normalized, show the raw values that were considered equal
+            let raw_values: Vec<&str> = grouping
+                .groups()
+                .iter()
+                .filter_map(|g| g.emissions_map().get(id))
+                .map(|e| e.value())
+                .unique()
+                .collect();
+            if some_emission.normalized.is_some() && raw_values.len() > 1 {
+                println!("\t{}", "Raw values:".dimmed());
+                for raw in raw_values {
+                    println!("\t  {}", raw.replace('\n', "\n\t  ").dimmed());
+                }
+            }
+        }
+    }
+    println!();
+}
+
+pub fn print_foreign_seed_matches(matches: &[ForeignSeedMatch]) {
+    println!(
+        "{} {}\n",
+        "Emissions matching another student's seed:".bold(),
+        matches.len().to_string().underline()
+    );
+
+    for m in matches {
+        println!(
+            "\t{} emitted \"{}\" expected for SID {}",
+            format_submitters(m.group.submission().submitters()),
+            m.emission_id.as_str().italic(),
+            m.matches_sids.iter().join(", ").red()
+        );
+    }
+    println!();
+}
+
+pub fn print_authenticity_findings(findings: &[AuthenticityFinding], groups: &[EmissionsGroup]) {
+    println!(
+        "{} {}\n",
+        "Emissions failing authentication:".bold(),
+        findings.len().to_string().underline()
+    );
+
+    for finding in findings {
+        print!(
+            "\t{} \"{}\": {}",
+            format_submitters(finding.group.submission().submitters()),
+            finding.emission_id.as_str().italic(),
+            finding.issue.to_string().red()
+        );
+        if let AuthenticityIssue::Replayed { other_groups } = &finding.issue {
+            let others = other_groups
+                .iter()
+                .map(|i| format_submitters(groups[*i].submission().submitters()))
+                .join("; ");
+            print!(" also submitted by {}", others);
         }
+        println!();
+    }
+    println!();
+}
+
+pub fn print_evaluation(evaluation: &Evaluation) {
+    println!("{}", "Planted rings:".bold());
+    for ring in &evaluation.rings {
+        let status = if ring.recovered {
+            "recovered".green()
+        } else if ring.found_pairs > 0 {
+            "partial".yellow()
+        } else {
+            "missed".red()
+        };
+        println!(
+            "\t[{}] {} ({}/{} pairs)",
+            status,
+            ring.members.iter().join(", "),
+            ring.found_pairs,
+            ring.total_pairs
+        );
     }
     println!();
+
+    println!(
+        "{} {}",
+        "False-positive groups:".bold(),
+        evaluation
+            .false_positive_groups
+            .len()
+            .to_string()
+            .underline()
+    );
+    for members in &evaluation.false_positive_groups {
+        println!("\t{}", members.iter().join(", ").red());
+    }
+    println!();
+
+    println!(
+        "Pairs: {} true positive, {} false positive, {} false negative",
+        evaluation.true_positives.to_string().green(),
+        evaluation.false_positives.to_string().red(),
+        evaluation.false_negatives.to_string().yellow()
+    );
+    println!(
+        "Precision: {} | Recall: {} | F1: {}",
+        format!("{:.3}", evaluation.precision()).bold(),
+        format!("{:.3}", evaluation.recall()).bold(),
+        format!("{:.3}", evaluation.f1()).bold()
+    );
+}
+
+pub fn print_diagnoses(submissions: usize, diagnoses: &[Diagnosis]) {
+    println!(
+        "{} {} of {}\n",
+        "Submissions with missing emissions:".bold(),
+        diagnoses.len().to_string().underline(),
+        submissions
+    );
+
+    let counts = diagnoses
+        .iter()
+        .flat_map(|d| &d.issues)
+        .map(|i| i.category())
+        .counts();
+    for (category, count) in counts.iter().sorted() {
+        println!("\t{}: {}", category, count.to_string().bold());
+    }
+    if !counts.is_empty() {
+        println!();
+    }
+
+    for diagnosis in diagnoses {
+        println!(
+            "{} ({} emissions)",
+            format_submitters(diagnosis.submission.submitters()).bold(),
+            diagnosis.emissions
+        );
+        for issue in &diagnosis.issues {
+            println!("\t{}", issue.to_string().red());
+            for line in issue_details(issue) {
+                println!("\t  {}", line.dimmed());
+            }
+        }
+    }
+}
+
+pub fn print_coverage(coverage: &Coverage) {
+    // IDs can be long, so columns are numbered with a legend
+    println!("{}", "Emission IDs:".bold());
+    for (i, id) in coverage.ids.iter().enumerate() {
+        println!("\t{:>3}  {}", i + 1, id.as_str().italic());
+    }
+    println!();
+
+    let names: Vec<String> = coverage
+        .rows
+        .iter()
+        .map(|r| format_submitters(r.submission.submitters()))
+        .collect();
+    let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let header = (1..=coverage.ids.len())
+        .map(|i| format!("{:>3}", i))
+        .join("");
+    println!("{:width$} {}", "", header.bold(), width = width);
+    for (name, row) in names.iter().zip(&coverage.rows) {
+        let cells = row
+            .cells
+            .iter()
+            .map(|c| match c {
+                CoverageCell::Present => "  ✓".green(),
+                CoverageCell::Absent => "  ·".dimmed(),
+                CoverageCell::DecodeError => "  ✗".red(),
+            })
+            .join("");
+        println!("{:width$} {}", name, cells, width = width);
+    }
+    println!(
+        "\n{} present  {} absent  {} decode error\n",
+        "✓".green(),
+        "·".dimmed(),
+        "✗".red()
+    );
+}
+
+pub fn print_low_coverage(below: &[(Symbol, usize)], submissions: usize, threshold: f64) {
+    println!(
+        "{} {}\n",
+        format!(
+            "Emission IDs emitted by fewer than {:.1}% of submissions:",
+            threshold * 100.0
+        )
+        .bold(),
+        below.len().to_string().underline()
+    );
+    for (id, count) in below {
+        println!(
+            "\t\"{}\": {} of {}",
+            id.as_str().italic(),
+            count.to_string().red(),
+            submissions
+        );
+    }
+    println!();
+}
+
+pub fn print_pseudonyms(students: &[(&String, &Submitter)]) {
+    for (pseudonym, submitter) in students {
+        println!(
+            "{}\t{} <{}>",
+            pseudonym.bold(),
+            format_submitters(std::slice::from_ref(*submitter)),
+            submitter.email
+        );
+    }
+}
+
+fn print_review_status(status: &ReviewStatus) {
+    let previously = |review: &ReviewEntry| match review.note.as_str() {
+        "" => review.decision.to_string(),
+        note => format!("{}: {}", review.decision, note),
+    };
+    let line = match status {
+        ReviewStatus::New => "New".green().bold(),
+        ReviewStatus::Reviewed(previous) => format!("Previously {}", previously(previous)).dimmed(),
+        ReviewStatus::Grown { previous, added } => format!(
+            "Grew since review by {} (was {})",
+            added.join(", "),
+            previously(previous)
+        )
+        .yellow()
+        .bold(),
+        ReviewStatus::Changed(previous) => {
+            format!("Changed since review (was {})", previously(previous)).yellow()
+        }
+    };
+    println!("\t{}", line);
+}
+
+fn format_submitters(submitters: &[Submitter]) -> String {
+    submitters
+        .iter()
+        .map(|s| {
+            format!(
+                "{} (SID: {})",
+                s.name,
+                s.sid.as_ref().unwrap_or(&"Unknown SID".to_string())
+            )
+        })
+        .join(", ")
 }
@@ -0,0 +1,190 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use zip::{read::ZipFile, ZipArchive};
+
+use crate::gradescope::{stream::ExportStream, types::Export};
+
+const METADATA_FILE: &str = "submission_metadata.yml";
+
+/// A Gradescope ZIP export: a folder with `submission_metadata.yml` next to one
+/// `submission_<id>/` folder of submitted files per submission, keyed like the metadata.
+pub struct ExportArchive {
+    archive: ZipArchive<File>,
+    /// The folder holding the metadata (e.g. `assignment_123_export/`), or empty if it's at
+    /// the root of the archive
+    prefix: String,
+}
+
+impl ExportArchive {
+    pub fn open<T: AsRef<Path>>(path: T) -> Result<ExportArchive, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+        let metadata = archive
+            .file_names()
+            .filter(|name| name.rsplit('/').next() == Some(METADATA_FILE))
+            .min_by_key(|name| name.len())
+            .ok_or(format!("No {} in the archive", METADATA_FILE))?;
+        let prefix = metadata
+            .strip_suffix(METADATA_FILE)
+            .unwrap_or("")
+            .to_string();
+        Ok(ExportArchive { archive, prefix })
+    }
+
+    fn metadata(&mut self) -> Result<ZipFile<'_, File>, String> {
+        self.archive
+            .by_name(&format!("{}{}", self.prefix, METADATA_FILE))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Loads the whole export from the archive's metadata.
+    pub fn load(&mut self) -> Result<Export, String> {
+        let mut data = String::new();
+        self.metadata()?
+            .read_to_string(&mut data)
+            .map_err(|e| e.to_string())?;
+        serde_yaml::from_str::<Export>(&data).map_err(|e| e.to_string())
+    }
+
+    /// Streams the submissions out of the archive's metadata (see `ExportStream`).
+    pub fn stream(&mut self) -> Result<ExportStream<BufReader<ZipFile<'_, File>>>, String> {
+        Ok(ExportStream::new(BufReader::new(self.metadata()?)))
+    }
+
+    /// Copies the files of the submission with the given key (e.g. `submission_1234`) into
+    /// `dest/<key>/`, returning how many files were copied.
+    pub fn extract_submission<T: AsRef<Path>>(
+        &mut self,
+        key: &str,
+        dest: T,
+    ) -> Result<usize, String> {
+        let folder = format!("{}{}/", self.prefix, key);
+        let mut copied = 0;
+        for i in 0..self.archive.len() {
+            let mut file = self.archive.by_index(i).map_err(|e| e.to_string())?;
+            if file.is_dir() || !file.name().starts_with(&folder) {
+                continue;
+            }
+            // Skip anything that would land outside the destination (e.g. `../`)
+            let Some(relative) = file
+                .enclosed_name()
+                .and_then(|p| p.strip_prefix(&self.prefix).ok().map(|p| p.to_path_buf()))
+            else {
+                continue;
+            };
+
+            let path = dest.as_ref().join(relative);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut out = File::create(&path).map_err(|e| e.to_string())?;
+            io::copy(&mut file, &mut out).map_err(|e| e.to_string())?;
+            copied += 1;
+        }
+        Ok(copied)
+    }
+
+    /// Reads the text files of the submission with the given key, as (path in the
+    /// submission's folder, contents) pairs. Files that aren't UTF-8 (e.g. images) are skipped.
+    pub fn read_submission(&mut self, key: &str) -> Result<Vec<(String, String)>, String> {
+        let folder = format!("{}{}/", self.prefix, key);
+        let mut files = vec![];
+        for i in 0..self.archive.len() {
+            let mut file = self.archive.by_index(i).map_err(|e| e.to_string())?;
+            let Some(name) = file.name().strip_prefix(&folder).map(str::to_string) else {
+                continue;
+            };
+            if file.is_dir() {
+                continue;
+            }
+            let mut contents = String::new();
+            if file.read_to_string(&mut contents).is_ok() {
+                files.push((name, contents));
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Whether `path` looks like a ZIP export rather than a bare `submission_metadata.yml`.
+pub fn is_zip<T: AsRef<Path>>(path: T) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+    use crate::gradescope::types::SubmissionTrait;
+
+    const METADATA: &str = r#"---
+submission_1:
+  :submitters:
+  - :name: Alice
+    :sid: '1'
+    :email: alice@example.edu
+  :created_at: 2024-09-10 21:15:39.155688000 -04:00
+  :score: 5.0
+  :status: processed
+  :results:
+    score: 5.0
+    tests:
+    - name: Emissions
+      number: '99.1'
+      output: "*x*MQ=="
+      status: passed
+    visibility: visible
+    leaderboard: []
+    execution_time: 1.0
+  :history: []
+"#;
+
+    #[test]
+    fn test_archive_loads_and_extracts() {
+        let dir = std::env::temp_dir().join(format!("rufus-archive-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("export.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        for (name, contents) in [
+            ("assignment_1_export/submission_metadata.yml", METADATA),
+            ("assignment_1_export/submission_1/solution.py", "print(1)"),
+            ("assignment_1_export/submission_10/solution.py", "print(10)"),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut archive = ExportArchive::open(&path).unwrap();
+        let export = archive.load().unwrap();
+        assert_eq!(export["submission_1"].submitters[0].name, "Alice");
+        let (key, streamed) = archive.stream().unwrap().next().unwrap().unwrap();
+        assert_eq!(key, "submission_1");
+        assert_eq!(streamed.parse_emissions().len(), 1);
+
+        // Only the submission's own folder is copied
+        let dest = dir.join("packet");
+        assert_eq!(
+            archive.extract_submission("submission_1", &dest).unwrap(),
+            1
+        );
+        let copied = fs::read_to_string(dest.join("submission_1/solution.py")).unwrap();
+        assert_eq!(copied, "print(1)");
+        assert!(!dest.join("submission_10").exists());
+        assert_eq!(
+            archive.read_submission("submission_10").unwrap(),
+            vec![("solution.py".to_string(), "print(10)".to_string())]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
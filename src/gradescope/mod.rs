@@ -1,3 +1,5 @@
 #![allow(dead_code)] // this is a library, so we don't need to worry about dead code
+pub mod archive;
 pub mod loaders;
+pub mod stream;
 pub mod types;
@@ -0,0 +1,271 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::gradescope::types::{
+    is_emission_test, FailedResults, LatestSubmission, ProcessedResults, Results, Score, Submitter,
+    Test,
+};
+
+/// Streams the submissions out of a `submission_metadata.yml` export one at a time, so the
+/// whole file never has to be in memory.
+///
+/// The export is a single top-level mapping from submission IDs (`submission_1234:`) to
+/// submissions, so each submission's block runs from its unindented key to the next one.
+/// Each block is deserialized on its own, keeping only what's needed to extract emissions:
+/// the submitters, timestamps, score, status, the emission tests, and the scores of every
+/// other test (with the output of the ones that failed). Leaderboards, history, and tests'
+/// extra data and formatting are skipped while parsing, and the output of every passing test
+/// is dropped as soon as its test is parsed.
+pub struct ExportStream<R: BufRead> {
+    lines: Lines<R>,
+    key: Option<String>,
+    block: String,
+}
+
+/// Opens an export for streaming.
+pub fn stream_export<T: AsRef<Path>>(path: T) -> Result<ExportStream<BufReader<File>>, String> {
+    File::open(path)
+        .map(|f| ExportStream::new(BufReader::new(f)))
+        .map_err(|e| e.to_string())
+}
+
+impl<R: BufRead> ExportStream<R> {
+    pub fn new(reader: R) -> Self {
+        ExportStream {
+            lines: reader.lines(),
+            key: None,
+            block: String::new(),
+        }
+    }
+
+    /// Returns the submission key if `line` starts a new top-level entry.
+    fn top_level_key(line: &str) -> Option<String> {
+        let starts_entry = line
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_whitespace() && c != '#' && c != '-' && c != '.');
+        if !starts_entry {
+            return None;
+        }
+
+        let key = line.trim_end().strip_suffix(':')?;
+        Some(key.trim_matches(['"', '\'']).to_string())
+    }
+
+    fn finish_block(&mut self, key: String) -> Result<(String, LatestSubmission), String> {
+        let block = std::mem::take(&mut self.block);
+        serde_yaml::from_str::<LeanSubmission>(&block)
+            .map(|s| (key.clone(), s.into()))
+            .map_err(|e| format!("{}: {}", key, e))
+    }
+}
+
+impl<R: BufRead> Iterator for ExportStream<R> {
+    type Item = Result<(String, LatestSubmission), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next() {
+                Some(Ok(line)) => match Self::top_level_key(&line) {
+                    Some(next_key) => {
+                        if let Some(key) = self.key.replace(next_key) {
+                            return Some(self.finish_block(key));
+                        }
+                    }
+                    None => {
+                        // Anything before the first key (e.g. `---`) isn't part of a block
+                        if self.key.is_some() {
+                            self.block.push_str(&line);
+                            self.block.push('\n');
+                        }
+                    }
+                },
+                Some(Err(e)) => return Some(Err(e.to_string())),
+                None => {
+                    let key = self.key.take()?;
+                    return Some(self.finish_block(key));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LeanSubmission {
+    #[serde(rename = ":submitters")]
+    submitters: Vec<Submitter>,
+
+    #[serde(rename = ":created_at")]
+    created_at: String,
+
+    #[serde(rename = ":score")]
+    score: Score,
+
+    #[serde(rename = ":status")]
+    status: String,
+
+    #[serde(rename = ":results")]
+    results: Option<LeanResults>,
+}
+
+/// Processed and failed results in one struct, so that telling them apart doesn't require
+/// buffering the whole block like an untagged enum would.
+#[derive(Deserialize)]
+struct LeanResults {
+    score: Option<Score>,
+    tests: Option<Vec<LeanTest>>,
+    output: Option<String>,
+}
+
+/// The fields of a test that emissions and scores need. Anything else (e.g. `extra_data`,
+/// which can be large) is skipped without being built.
+#[derive(Deserialize)]
+struct LeanTest {
+    name: String,
+    tags: Option<Vec<String>>,
+    score: Option<Score>,
+    number: String,
+    output: Option<String>,
+    status: String,
+    max_score: Option<Score>,
+}
+
+/// Keeps a graded test's output (usually the bulk of the export) only if it failed, since
+/// `--failed-outputs` compares those. Emission tests keep their output.
+impl From<LeanTest> for Test {
+    fn from(lean: LeanTest) -> Self {
+        let mut test = Test {
+            name: lean.name,
+            tags: lean.tags,
+            score: lean.score,
+            number: lean.number,
+            output: None,
+            status: lean.status,
+            max_score: lean.max_score,
+            extra_data: None,
+            visibility: None,
+            name_format: None,
+            output_format: None,
+        };
+        if is_emission_test(&test) || test.is_incorrect() {
+            test.output = lean.output;
+        }
+        test
+    }
+}
+
+impl From<LeanSubmission> for LatestSubmission {
+    fn from(lean: LeanSubmission) -> Self {
+        let results = lean.results.map(|r| match r.tests {
+            Some(tests) => Results::Processed(ProcessedResults {
+                score: r.score.unwrap_or(lean.score),
+                tests: tests.into_iter().map(Test::from).collect(),
+                output: r.output,
+                extra_data: None,
+                visibility: String::new(),
+                leaderboard: vec![],
+                output_format: None,
+                execution_time: 0.0,
+                test_name_format: None,
+                test_output_format: None,
+            }),
+            None => Results::Failed(FailedResults { output: r.output }),
+        });
+
+        LatestSubmission {
+            submitters: lean.submitters,
+            created_at: lean.created_at,
+            score: lean.score,
+            status: lean.status,
+            results,
+            history: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradescope::types::{Export, SubmissionTrait};
+
+    const EXPORT: &str = r#"---
+submission_1:
+  :submitters:
+  - :name: Alice
+    :sid: '1'
+    :email: alice@example.edu
+  :created_at: 2024-09-10 21:15:39.155688000 -04:00
+  :score: 5.0
+  :status: processed
+  :results:
+    score: 5.0
+    tests:
+    - name: Part 1
+      number: '1.1'
+      max_score: 10.0
+      output: |-
+        a long output
+
+        with a blank line
+      status: passed
+      extra_data:
+        traceback: a long traceback
+    - name: Emissions
+      number: '99.1'
+      output: "*x*MQ=="
+      status: passed
+    visibility: visible
+    leaderboard: []
+    execution_time: 1.0
+  :history: []
+"submission_2":
+  :submitters:
+  - :name: Bob
+    :sid: '2'
+    :email: bob@example.edu
+  :created_at: 2024-09-10 21:16:39.155688000 -04:00
+  :score: 0.0
+  :status: failed
+  :results:
+    output: crashed
+  :history: []
+"#;
+
+    #[test]
+    fn test_stream_matches_full_load() {
+        let streamed: Vec<(String, LatestSubmission)> = ExportStream::new(EXPORT.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let full: Export = serde_yaml::from_str(EXPORT).unwrap();
+
+        assert_eq!(streamed.len(), full.len());
+        for (key, submission) in &streamed {
+            let loaded = &full[key];
+            assert_eq!(submission.submitters[0].name, loaded.submitters[0].name);
+            assert_eq!(submission.created_at, loaded.created_at);
+            assert_eq!(submission.max_score(), loaded.max_score());
+
+            let streamed_emissions = submission.parse_emissions();
+            let loaded_emissions = loaded.parse_emissions();
+            assert_eq!(streamed_emissions.len(), loaded_emissions.len());
+            assert!(streamed_emissions.is_empty() || streamed_emissions.matches(&loaded_emissions));
+        }
+
+        // The passing graded test loses its output
+        match &streamed[0].1.results {
+            Some(Results::Processed(r)) => {
+                assert_eq!(r.tests.len(), 2);
+                assert!(r.tests[0].output.is_none());
+                assert!(r.tests[0].extra_data.is_none());
+                assert!(r.tests[1].output.is_some());
+            }
+            _ => panic!("expected processed results"),
+        }
+        assert!(matches!(streamed[1].1.results, Some(Results::Failed(_))));
+    }
+}
@@ -1,11 +1,12 @@
-use serde::Deserialize;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::rufus::{Emission, EmissionParseError, EmissionsGroup};
 
 pub type Export = HashMap<String, LatestSubmission>;
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum Submission {
     Latest(LatestSubmission),
@@ -14,6 +15,22 @@ pub enum Submission {
 
 static EMISSION_NUMBER_PREFIX: &str = "99.";
 
+/// Whether a test holds emissions (by its `99.x` number) rather than being a graded test.
+pub fn is_emission_test(test: &Test) -> bool {
+    test.number.starts_with(EMISSION_NUMBER_PREFIX)
+}
+
+/// A graded test tagged `emission:<id>` is the parent test of the emission with that ID.
+static EMISSION_TAG_PREFIX: &str = "emission:";
+
+/// The format of Gradescope's `created_at` timestamps, e.g.
+/// `2024-09-10 21:15:39.155688000 -04:00`.
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f %:z";
+
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_str(timestamp.trim(), TIMESTAMP_FORMAT).ok()
+}
+
 pub trait SubmissionTrait {
     fn submitters(&self) -> &Vec<Submitter>;
     fn created_at(&self) -> &String;
@@ -21,48 +38,67 @@ pub trait SubmissionTrait {
     fn status(&self) -> &String;
     fn results(&self) -> &Option<Results>;
 
+    /// The most points the submission could have gotten: the sum of its graded tests' max
+    /// scores, or `None` if its results don't give any.
+    fn max_score(&self) -> Option<Score> {
+        match self.results() {
+            Some(Results::Processed(results)) => results
+                .tests
+                .iter()
+                .filter(|t| !is_emission_test(t))
+                .filter_map(|t| t.max_score)
+                .reduce(|a, b| a + b),
+            _ => None,
+        }
+    }
+
+    /// Whether the submission got every point it could have (as far as its results say).
+    fn has_full_marks(&self) -> bool {
+        self.max_score().is_some_and(|max| *self.score() >= max)
+    }
+
+    /// The graded test that checks the emission with ID `id`: the one tagged `emission:<id>`,
+    /// or else the one whose number or name (ignoring case) is `id`.
+    fn parent_test(&self, id: &str) -> Option<&Test> {
+        let Some(Results::Processed(results)) = self.results() else {
+            return None;
+        };
+        let graded = || results.tests.iter().filter(|t| !is_emission_test(t));
+        graded()
+            .find(|t| t.emission_tags().any(|tag| tag == id))
+            .or_else(|| graded().find(|t| t.number == id || t.name.eq_ignore_ascii_case(id)))
+    }
+
+    /// When the submission was made, if its `created_at` is a timestamp Rufus understands.
+    fn submitted_at(&self) -> Option<DateTime<FixedOffset>> {
+        parse_timestamp(self.created_at())
+    }
+
+    /// Extracts every emission line from this submission, keeping parse failures so the
+    /// caller can decide how to report them. Sources that don't come from Gradescope
+    /// results (e.g. a CSV of emissions) should override this.
+    fn emissions(&self) -> Vec<Result<Emission, EmissionParseError>> {
+        match self.results().as_ref() {
+            Some(Results::Processed(processed_results)) => processed_results
+                .tests
+                .iter()
+                .filter(|t| is_emission_test(t))
+                .filter_map(|t| t.output.as_ref())
+                .flat_map(|output| output.lines().map(Emission::parse))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
     fn parse_emissions<'a>(&'a self) -> EmissionsGroup<'a>
     where
         Self: Sized,
     {
-        // pub fn parse_emissions<'a>(&'a self) -> EmissionsGroup<'a> {
-        match self.results().as_ref() {
-            Some(Results::Processed(processed_results)) => {
-                let emissions: Vec<Result<Emission, EmissionParseError>> = processed_results
-                    .tests
-                    .iter()
-                    .filter(|t| t.number.starts_with(EMISSION_NUMBER_PREFIX) && t.output.is_some())
-                    .flat_map(|t| {
-                        t.output
-                            .as_ref()
-                            .unwrap() // unwrap because we just checked it's Some
-                            .lines()
-                            .map(|line| Emission::parse(line))
-                            .collect::<Vec<_>>()
-                    })
-                    .collect::<Vec<_>>();
-
-                // Filter out any errors in parsing emissions
-                let emissions: Vec<Emission> = emissions
-                    .into_iter()
-                    .filter_map(|e| match e {
-                        Ok(emission) => Some(emission),
-                        Err(e) => {
-                            eprintln!("Error parsing emission: {}", e);
-                            None
-                        }
-                    })
-                    .collect();
-
-                EmissionsGroup::new(self, emissions)
-            }
-            _ => EmissionsGroup::new(self, vec![]),
-        }
-        // }
+        EmissionsGroup::from_submission(self)
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct LatestSubmission {
     #[serde(rename = ":submitters")]
     pub submitters: Vec<Submitter>,
@@ -83,7 +119,7 @@ pub struct LatestSubmission {
     pub history: Vec<HistoricalSubmission>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct HistoricalSubmission {
     #[serde(rename = ":submitters")]
     pub submitters: Vec<Submitter>,
@@ -148,7 +184,7 @@ impl SubmissionTrait for LatestSubmission {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Submitter {
     #[serde(rename = ":name")]
     pub name: String,
@@ -160,8 +196,19 @@ pub struct Submitter {
     pub email: String,
 }
 
+impl Submitter {
+    /// A key identifying the student: their SID, or their email (then name) if there's no SID.
+    pub fn key(&self) -> &str {
+        match &self.sid {
+            Some(sid) => sid,
+            None if !self.email.is_empty() => &self.email,
+            None => &self.name,
+        }
+    }
+}
+
 pub type Score = f32;
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct ProcessedResults {
     pub score: Score,
     pub tests: Vec<Test>,
@@ -176,19 +223,20 @@ pub struct ProcessedResults {
     pub test_output_format: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct FailedResults {
     pub output: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum Results {
     Processed(ProcessedResults),
     Failed(FailedResults),
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Test {
     pub name: String,
     pub tags: Option<Vec<String>>,
@@ -203,7 +251,23 @@ pub struct Test {
     pub output_format: Option<OutputFormat>,
 }
 
-#[derive(Deserialize, Debug)]
+impl Test {
+    /// The emission IDs this test is tagged as the parent of.
+    pub fn emission_tags(&self) -> impl Iterator<Item = &str> {
+        self.tags
+            .iter()
+            .flatten()
+            .filter_map(|tag| tag.strip_prefix(EMISSION_TAG_PREFIX))
+    }
+
+    /// Whether the test failed or lost points.
+    pub fn is_incorrect(&self) -> bool {
+        self.status == "failed"
+            || matches!((self.score, self.max_score), (Some(score), Some(max)) if score < max)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum Visibility {
     Hidden,
@@ -212,7 +276,7 @@ pub enum Visibility {
     Visible,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub enum OutputFormat {
     #[serde(rename = "text")]
     Text,
@@ -226,20 +290,20 @@ pub enum OutputFormat {
     ANSI,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct LeaderboardItem {
     pub name: String,
     pub value: LeaderboardValue,
     pub order: Option<SortOrder>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub enum LeaderboardValue {
     Float(f32),
     String(String),
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub enum SortOrder {
     #[serde(rename = "asc")]
     Ascending,
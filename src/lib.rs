@@ -0,0 +1,5 @@
+pub mod cli;
+pub mod gradescope;
+pub mod rufus;
+pub mod sources;
+pub mod synth;
@@ -1,28 +1,29 @@
-mod cli;
-mod gradescope;
-mod rufus;
-
 use clap::Parser;
 
-use crate::cli::clap::{Cli, Command};
+use rufus::cli::{
+    self,
+    clap::{Cli, Command},
+};
 
 fn main() {
     let args = Cli::parse();
 
     match &args.command {
-        Command::Count { filepaths } => cli::handlers::handle_count(&filepaths),
-        Command::Hunt {
+        Command::Count {
             filepaths,
-            group_size,
-            show_emissions,
-            min_size,
-            exact,
-        } => cli::handlers::handle_hunt(
-            &filepaths,
-            group_size,
-            &show_emissions,
-            &(*min_size as usize),
-            &exact,
-        ),
+            source,
+            format,
+        } => cli::handlers::handle_count(filepaths, source, *format),
+        Command::Hunt(hunt_args) => cli::handlers::handle_hunt(hunt_args),
+        Command::Coverage(coverage_args) => cli::handlers::handle_coverage(coverage_args),
+        Command::Diagnose(diagnose_args) => cli::handlers::handle_diagnose(diagnose_args),
+        Command::Evaluate(evaluate_args) => cli::handlers::handle_evaluate(evaluate_args),
+        Command::Packet(packet_args) => cli::handlers::handle_packet(packet_args),
+        Command::Review(review_args) => cli::handlers::handle_review(review_args),
+        Command::Deanonymize(deanonymize_args) => {
+            cli::handlers::handle_deanonymize(deanonymize_args)
+        }
+        Command::Seed(seed_args) => cli::handlers::handle_seed(seed_args),
+        Command::Synth(synth_args) => cli::handlers::handle_synth(synth_args),
     }
 }
@@ -0,0 +1,85 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::to_hex;
+use crate::gradescope::types::Submitter;
+
+/// The number of hex characters kept from the keyed hash. 12 (48 bits) keeps pseudonyms short
+/// while making collisions within a course practically impossible.
+const PSEUDONYM_LENGTH: usize = 12;
+
+/// Replaces submitters with stable pseudonyms, so reports can be shared without revealing who
+/// is in them.
+///
+/// A pseudonym is `anon-` followed by hex `HMAC-SHA256(key, submitter key)`, truncated. The
+/// same key always gives a student the same pseudonym, so anonymized reports from different
+/// runs line up, and without the key pseudonyms can't be traced back to students.
+pub struct Anonymizer {
+    key: Vec<u8>,
+}
+
+impl Anonymizer {
+    pub fn new(key: &[u8]) -> Self {
+        Anonymizer { key: key.to_vec() }
+    }
+
+    pub fn pseudonym(&self, submitter: &Submitter) -> String {
+        self.pseudonym_for_key(submitter.key())
+    }
+
+    /// The pseudonym of the student with the given key (see `Submitter::key`), e.g. a SID.
+    pub fn pseudonym_for_key(&self, key: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(key.as_bytes());
+        let hash = to_hex(&mac.finalize().into_bytes());
+        format!("anon-{}", &hash[..PSEUDONYM_LENGTH])
+    }
+
+    /// The submitter with their name and SID replaced by their pseudonym and their email
+    /// removed.
+    pub fn anonymize(&self, submitter: &Submitter) -> Submitter {
+        let pseudonym = self.pseudonym(submitter);
+        Submitter {
+            name: pseudonym.clone(),
+            sid: Some(pseudonym),
+            email: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submitter(name: &str, sid: Option<&str>, email: &str) -> Submitter {
+        Submitter {
+            name: name.to_string(),
+            sid: sid.map(|s| s.to_string()),
+            email: email.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_pseudonyms_are_stable_and_keyed() {
+        let alice = submitter("Alice", Some("1"), "alice@example.edu");
+        let anonymizer = Anonymizer::new(b"key");
+        let pseudonym = anonymizer.pseudonym(&alice);
+        assert!(pseudonym.starts_with("anon-"));
+        assert_eq!(pseudonym.len(), "anon-".len() + PSEUDONYM_LENGTH);
+
+        // The pseudonym only depends on the key and who the student is
+        let renamed = submitter("Alice Smith", Some("1"), "asmith@example.edu");
+        assert_eq!(anonymizer.pseudonym(&renamed), pseudonym);
+        assert_ne!(Anonymizer::new(b"other key").pseudonym(&alice), pseudonym);
+        assert_ne!(
+            anonymizer.pseudonym(&submitter("Bob", Some("2"), "bob@example.edu")),
+            pseudonym
+        );
+
+        let anonymized = anonymizer.anonymize(&alice);
+        assert_eq!(anonymized.name, pseudonym);
+        assert_eq!(anonymized.key(), pseudonym);
+        assert!(anonymized.email.is_empty());
+    }
+}
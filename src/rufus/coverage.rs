@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use crate::gradescope::types::SubmissionTrait;
+
+use super::{EmissionParseError, Symbol};
+
+/// Whether a submission emitted an ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageCell {
+    Present,
+    Absent,
+    /// The ID was emitted, but its value didn't decode
+    DecodeError,
+}
+
+impl CoverageCell {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoverageCell::Present => "present",
+            CoverageCell::Absent => "absent",
+            CoverageCell::DecodeError => "decode_error",
+        }
+    }
+}
+
+/// One submission's row of the coverage matrix, with a cell per ID in `Coverage::ids`.
+pub struct CoverageRow<'a> {
+    pub submission: &'a dyn SubmissionTrait,
+    pub cells: Vec<CoverageCell>,
+}
+
+impl CoverageRow<'_> {
+    pub fn is_complete(&self) -> bool {
+        self.cells.iter().all(|c| *c == CoverageCell::Present)
+    }
+}
+
+/// A matrix of which submissions emitted which IDs. The IDs are every ID any submission
+/// emitted (even if it didn't decode), sorted.
+pub struct Coverage<'a> {
+    pub ids: Vec<Symbol>,
+    pub rows: Vec<CoverageRow<'a>>,
+}
+
+impl<'a> Coverage<'a> {
+    pub fn new(submissions: &[&'a dyn SubmissionTrait]) -> Self {
+        let emitted: Vec<(HashSet<Symbol>, HashSet<Symbol>)> = submissions
+            .iter()
+            .map(|s| {
+                let mut present = HashSet::new();
+                let mut undecoded = HashSet::new();
+                for emission in s.emissions() {
+                    match emission {
+                        Ok(emission) => {
+                            present.insert(emission.id());
+                        }
+                        Err(EmissionParseError::DecodeError { id, .. }) => {
+                            undecoded.insert(Symbol::intern(&id));
+                        }
+                        Err(EmissionParseError::FormatError(_)) => {}
+                    }
+                }
+                (present, undecoded)
+            })
+            .collect();
+        let ids: Vec<Symbol> = emitted
+            .iter()
+            .flat_map(|(present, undecoded)| present.iter().chain(undecoded))
+            .copied()
+            .unique()
+            .sorted_by_key(|id| id.as_str())
+            .collect();
+
+        let rows = submissions
+            .iter()
+            .zip(&emitted)
+            .map(|(submission, (present, undecoded))| CoverageRow {
+                submission: *submission,
+                cells: ids
+                    .iter()
+                    .map(|id| {
+                        if present.contains(id) {
+                            CoverageCell::Present
+                        } else if undecoded.contains(id) {
+                            CoverageCell::DecodeError
+                        } else {
+                            CoverageCell::Absent
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Coverage { ids, rows }
+    }
+
+    /// How many submissions emitted each ID (with a value that decoded).
+    pub fn counts(&self) -> Vec<(Symbol, usize)> {
+        self.ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let count = self
+                    .rows
+                    .iter()
+                    .filter(|r| r.cells[i] == CoverageCell::Present)
+                    .count();
+                (*id, count)
+            })
+            .collect()
+    }
+
+    /// The IDs emitted by fewer than `threshold` (a fraction) of the submissions, with how
+    /// many did emit them.
+    pub fn below(&self, threshold: f64) -> Vec<(Symbol, usize)> {
+        let total = self.rows.len() as f64;
+        self.counts()
+            .into_iter()
+            .filter(|(_, count)| (*count as f64) < threshold * total)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradescope::types::Export;
+
+    #[test]
+    fn test_coverage_of_malformed_export() {
+        let export: Export =
+            serde_yaml::from_str(include_str!("../../tests/fixtures/malformed.yml")).unwrap();
+        let submissions: Vec<&dyn SubmissionTrait> =
+            export.values().map(|s| s as &dyn SubmissionTrait).collect();
+        let coverage = Coverage::new(&submissions);
+
+        let ids: Vec<&str> = coverage.ids.iter().map(|id| id.as_str()).collect();
+        assert_eq!(ids, vec!["x", "y", "z"]);
+        assert_eq!(
+            coverage
+                .counts()
+                .iter()
+                .map(|(_, c)| *c)
+                .collect::<Vec<_>>(),
+            vec![2, 2, 0]
+        );
+        // z never decodes, and x and y are missing from one of three submissions
+        let below: Vec<&str> = coverage
+            .below(0.5)
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect();
+        assert_eq!(below, vec!["z"]);
+        assert_eq!(coverage.below(0.9).len(), 3);
+
+        let undecoded = coverage
+            .rows
+            .iter()
+            .filter(|r| r.cells[2] == CoverageCell::DecodeError)
+            .count();
+        assert_eq!(undecoded, 2);
+        assert!(coverage.rows.iter().all(|r| !r.is_complete()));
+    }
+}
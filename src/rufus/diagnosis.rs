@@ -0,0 +1,160 @@
+use std::collections::BTreeSet;
+
+use crate::gradescope::types::{is_emission_test, Results, SubmissionTrait};
+
+use super::{EmissionParseError, Symbol};
+
+/// Why a submission produced no emissions, or fewer than the rest of the class.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmissionIssue {
+    /// The submission has no results, e.g. the autograder never ran on it
+    Unprocessed { status: String },
+    /// The autograder failed to run, with whatever output it gave
+    AutograderFailure { output: Option<String> },
+    /// The autograder ran, but none of its tests were emission tests
+    MissingEmissionTests,
+    /// Emission lines that couldn't be parsed or decoded
+    ParseErrors(Vec<EmissionParseError>),
+    /// IDs that other submissions emitted but this one didn't
+    MissingIds(BTreeSet<Symbol>),
+}
+
+impl EmissionIssue {
+    /// A short, stable name for the kind of issue.
+    pub fn category(&self) -> &'static str {
+        match self {
+            EmissionIssue::Unprocessed { .. } => "unprocessed",
+            EmissionIssue::AutograderFailure { .. } => "autograder_failure",
+            EmissionIssue::MissingEmissionTests => "missing_emission_tests",
+            EmissionIssue::ParseErrors(_) => "parse_errors",
+            EmissionIssue::MissingIds(_) => "missing_ids",
+        }
+    }
+}
+
+impl std::fmt::Display for EmissionIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmissionIssue::Unprocessed { status } => write!(f, "no results (status: {})", status),
+            EmissionIssue::AutograderFailure { .. } => write!(f, "autograder failed"),
+            EmissionIssue::MissingEmissionTests => write!(f, "no emission tests in results"),
+            EmissionIssue::ParseErrors(errors) => {
+                write!(f, "{} emission line(s) failed to parse", errors.len())
+            }
+            EmissionIssue::MissingIds(ids) => write!(f, "missing {} emission ID(s)", ids.len()),
+        }
+    }
+}
+
+/// The emission issues of one submission.
+pub struct Diagnosis<'a> {
+    pub submission: &'a dyn SubmissionTrait,
+    /// The number of emissions that parsed
+    pub emissions: usize,
+    pub issues: Vec<EmissionIssue>,
+}
+
+/// Finds every submission with no or partial emissions, and why. A submission is partial if
+/// some of its emission lines don't parse or it's missing IDs that other submissions emitted.
+pub fn diagnose<'a>(submissions: &[&'a dyn SubmissionTrait]) -> Vec<Diagnosis<'a>> {
+    let parsed: Vec<(BTreeSet<Symbol>, Vec<EmissionParseError>)> = submissions
+        .iter()
+        .map(|s| {
+            let (emissions, errors): (Vec<_>, Vec<_>) =
+                s.emissions().into_iter().partition(|e| e.is_ok());
+            (
+                emissions.into_iter().flatten().map(|e| e.id()).collect(),
+                errors.into_iter().filter_map(|e| e.err()).collect(),
+            )
+        })
+        .collect();
+    let all_ids: BTreeSet<Symbol> = parsed.iter().flat_map(|(ids, _)| ids).copied().collect();
+
+    submissions
+        .iter()
+        .zip(parsed)
+        .filter_map(|(submission, (ids, errors))| {
+            let mut issues = vec![];
+            if ids.is_empty() && errors.is_empty() {
+                // Nothing was emitted at all, so find out why from the results
+                match submission.results() {
+                    None => issues.push(EmissionIssue::Unprocessed {
+                        status: submission.status().clone(),
+                    }),
+                    Some(Results::Failed(failed)) => {
+                        issues.push(EmissionIssue::AutograderFailure {
+                            output: failed.output.clone(),
+                        })
+                    }
+                    Some(Results::Processed(processed))
+                        if !processed.tests.iter().any(is_emission_test) =>
+                    {
+                        issues.push(EmissionIssue::MissingEmissionTests)
+                    }
+                    Some(Results::Processed(_)) => {}
+                }
+            }
+            // Without results every ID is missing, which goes without saying
+            let has_results = issues.is_empty();
+            if !errors.is_empty() {
+                issues.push(EmissionIssue::ParseErrors(errors));
+            }
+            let missing: BTreeSet<Symbol> = all_ids.difference(&ids).copied().collect();
+            if has_results && !missing.is_empty() {
+                issues.push(EmissionIssue::MissingIds(missing));
+            }
+
+            (!issues.is_empty()).then_some(Diagnosis {
+                submission: *submission,
+                emissions: ids.len(),
+                issues,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradescope::types::Export;
+
+    fn diagnose_export(export: &Export) -> Vec<(String, Vec<&'static str>)> {
+        let submissions: Vec<&dyn SubmissionTrait> =
+            export.values().map(|s| s as &dyn SubmissionTrait).collect();
+        let mut diagnoses: Vec<(String, Vec<&'static str>)> = diagnose(&submissions)
+            .iter()
+            .map(|d| {
+                (
+                    d.submission.submitters()[0].name.clone(),
+                    d.issues.iter().map(|i| i.category()).collect(),
+                )
+            })
+            .collect();
+        diagnoses.sort();
+        diagnoses
+    }
+
+    #[test]
+    fn test_diagnoses_missing_emissions() {
+        let failed: Export =
+            serde_yaml::from_str(include_str!("../../tests/fixtures/failed.yml")).unwrap();
+        assert_eq!(
+            diagnose_export(&failed),
+            vec![
+                ("Student B".to_string(), vec!["autograder_failure"]),
+                ("Student C".to_string(), vec!["unprocessed"]),
+            ]
+        );
+
+        let malformed: Export =
+            serde_yaml::from_str(include_str!("../../tests/fixtures/malformed.yml")).unwrap();
+        assert_eq!(
+            diagnose_export(&malformed),
+            vec![
+                ("Student A".to_string(), vec!["parse_errors"]),
+                ("Student B".to_string(), vec!["parse_errors"]),
+                ("Student C".to_string(), vec!["parse_errors", "missing_ids"]),
+            ]
+        );
+    }
+}
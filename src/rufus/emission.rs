@@ -1,55 +1,131 @@
 use base64::engine::general_purpose::STANDARD as b64;
 use base64::Engine;
 
-#[derive(Clone)]
+use super::{EmissionValue, Normalization, SchemaEntry, Symbol};
+
+#[derive(Clone, Debug)]
 pub struct Emission {
-    pub id: String,
-    pub value: String,
+    pub id: Symbol,
+    pub value: Symbol,
+
+    /// Canonical form of `value` used for comparisons, if a normalization was applied
+    pub normalized: Option<Symbol>,
+
+    /// `value` parsed according to the emission schema, if there is one
+    pub typed: Option<EmissionValue>,
+
+    /// The signature, if the emission was in the signed `*id*value*nonce*tag` format
+    pub signature: Option<Signature>,
+}
+
+/// The nonce and HMAC tag of a signed emission.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    pub nonce: String,
+    pub tag: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum EmissionParseError {
     FormatError(String),
-    DecodeError(String),
+    /// The value of the emission with ID `id` isn't valid base64-encoded UTF-8
+    DecodeError {
+        id: String,
+        message: String,
+    },
 }
 
 impl std::fmt::Display for EmissionParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             EmissionParseError::FormatError(msg) => write!(f, "Format Error: {}", msg),
-            EmissionParseError::DecodeError(msg) => write!(f, "Decode Error: {}", msg),
+            EmissionParseError::DecodeError { id, message } => {
+                write!(f, "Decode Error: {} (in \"{}\")", message, id)
+            }
         }
     }
 }
 
 impl Emission {
-    pub fn id(&self) -> &String {
-        &self.id
+    pub fn new(id: &str, value: &str) -> Self {
+        Emission {
+            id: Symbol::intern(id),
+            value: Symbol::intern(value),
+            normalized: None,
+            typed: None,
+            signature: None,
+        }
+    }
+
+    pub fn id(&self) -> Symbol {
+        self.id
+    }
+
+    pub fn value(&self) -> &'static str {
+        self.value.as_str()
+    }
+
+    /// The value used for comparisons: the normalized value if there is one, otherwise the
+    /// raw value.
+    pub fn key(&self) -> Symbol {
+        self.normalized.unwrap_or(self.value)
+    }
+
+    pub fn typed(&self) -> Option<&EmissionValue> {
+        self.typed.as_ref()
+    }
+
+    /// Normalizes the value for comparisons. An exact normalization leaves any canonical
+    /// form from the schema in place.
+    pub fn normalize(&mut self, normalization: &Normalization) {
+        if let Some(normalized) = normalization.apply(self.value()) {
+            self.normalized = Some(Symbol::intern(&normalized));
+        }
     }
 
-    pub fn value(&self) -> &String {
-        &self.value
+    /// Parses the value according to its schema entry, comparing by the typed value's
+    /// canonical form from then on.
+    pub fn apply_schema(&mut self, entry: &SchemaEntry) -> Result<(), String> {
+        let typed = EmissionValue::parse(self.value(), entry.value_type)?;
+        self.normalized = Some(Symbol::intern(&typed.canonical(entry.tolerance)));
+        self.typed = Some(typed);
+        Ok(())
+    }
+
+    /// The value for display: pretty-printed if typed, otherwise raw.
+    pub fn display_value(&self) -> String {
+        match &self.typed {
+            Some(typed) => typed.to_string(),
+            None => self.value().to_string(),
+        }
+    }
+
+    /// Whether the values are equal after normalization. Both are interned, so this is an
+    /// integer comparison.
+    pub fn matches(&self, other: &Emission) -> bool {
+        self.key() == other.key()
     }
 
     fn decode_b64_str(encoded_string: &str) -> Result<String, String> {
         let decoded_bytes = b64.decode(encoded_string).map_err(|e| e.to_string())?;
-        Ok(String::from_utf8(decoded_bytes).map_err(|e| e.to_string())?)
+        String::from_utf8(decoded_bytes).map_err(|e| e.to_string())
     }
 
     pub fn parse(emission_str: &str) -> Result<Emission, EmissionParseError> {
-        let regex = regex::Regex::new(r"\*(.*?)\*(.*)").unwrap();
+        let regex = regex::Regex::new(r"\*(.*?)\*([^*]*)(?:\*([^*]*)\*([^*]*))?").unwrap();
 
         let caps = regex
             .captures(emission_str)
             .ok_or_else(|| EmissionParseError::FormatError("Invalid format".to_string()))?;
 
-        // We know there are 3 groups: the whole match, the id, and the encoded value
+        // There are 3 groups (the whole match, the id, and the encoded value), plus the nonce
+        // and tag if the emission is signed
         let id = caps
             .get(1)
             .ok_or(EmissionParseError::FormatError(
                 "ID not found in emission string".to_string(),
             ))? // use of the "Try operator" to return early if the ID is not found
-            .as_str()
-            .to_string();
+            .as_str();
 
         // Deserialize the bytes to get the value
         let value = Self::decode_b64_str(
@@ -59,8 +135,22 @@ impl Emission {
                 ))?
                 .as_str(),
         )
-        .map_err(|e| EmissionParseError::DecodeError(e))?;
+        .map_err(|message| EmissionParseError::DecodeError {
+            id: id.to_string(),
+            message,
+        })?;
+
+        let signature = match (caps.get(3), caps.get(4)) {
+            (Some(nonce), Some(tag)) => Some(Signature {
+                nonce: nonce.as_str().to_string(),
+                tag: tag.as_str().to_string(),
+            }),
+            _ => None,
+        };
 
-        Ok(Emission { id, value })
+        Ok(Emission {
+            signature,
+            ..Emission::new(id, &value)
+        })
     }
 }
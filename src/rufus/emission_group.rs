@@ -5,12 +5,12 @@ use std::collections::{
 
 use crate::gradescope::types::SubmissionTrait;
 
-use super::Emission;
+use super::{Emission, EmissionSchema, NormalizationConfig, Symbol};
 
 #[derive(Clone)]
 pub struct EmissionsGroup<'a> {
     submission: &'a dyn SubmissionTrait,
-    emissions_map: HashMap<String, Emission>,
+    emissions_map: HashMap<Symbol, Emission>,
 }
 
 impl<'a> EmissionsGroup<'a> {
@@ -19,8 +19,48 @@ impl<'a> EmissionsGroup<'a> {
             submission,
             emissions_map: emissions
                 .into_iter()
-                .map(|e| (e.id().clone(), e))
-                .collect::<HashMap<String, Emission>>(),
+                .map(|e| (e.id(), e))
+                .collect::<HashMap<Symbol, Emission>>(),
+        }
+    }
+
+    /// Builds the group for a submission from its emissions, reporting (and skipping) any
+    /// emissions that failed to parse.
+    pub fn from_submission(submission: &'a dyn SubmissionTrait) -> Self {
+        let emissions: Vec<Emission> = submission
+            .emissions()
+            .into_iter()
+            .filter_map(|e| match e {
+                Ok(emission) => Some(emission),
+                Err(e) => {
+                    eprintln!("Error parsing emission: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        EmissionsGroup::new(submission, emissions)
+    }
+
+    /// Types every emission that has an entry in `schema`, reporting (and leaving untyped)
+    /// any values that don't parse.
+    pub fn apply_schema(&mut self, schema: &EmissionSchema) {
+        for (id, emission) in self.emissions_map.iter_mut() {
+            if let Some(entry) = schema.entry(id.as_str()) {
+                if let Err(e) = emission.apply_schema(entry) {
+                    eprintln!(
+                        "Error parsing emission \"{}\" as {}: {}",
+                        id, entry.value_type, e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Normalizes every emission according to its ID's entry in `config`.
+    pub fn normalize(&mut self, config: &NormalizationConfig) {
+        for (id, emission) in self.emissions_map.iter_mut() {
+            emission.normalize(config.for_id(id.as_str()));
         }
     }
 
@@ -28,19 +68,23 @@ impl<'a> EmissionsGroup<'a> {
         self.emissions_map.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.emissions_map.is_empty()
+    }
+
     pub fn submission(&self) -> &dyn SubmissionTrait {
         self.submission
     }
 
-    pub fn emissions_map(&self) -> &HashMap<String, Emission> {
+    pub fn emissions_map(&self) -> &HashMap<Symbol, Emission> {
         &self.emissions_map
     }
 
-    pub fn emission_ids(&self) -> Keys<'_, String, Emission> {
+    pub fn emission_ids(&self) -> Keys<'_, Symbol, Emission> {
         self.emissions_map.keys()
     }
 
-    pub fn emissions(&self) -> Values<'_, String, Emission> {
+    pub fn emissions(&self) -> Values<'_, Symbol, Emission> {
         self.emissions_map.values()
     }
 
@@ -48,52 +92,47 @@ impl<'a> EmissionsGroup<'a> {
         self.matches_on_ids(other, None, false)
     }
 
+    fn matches_on_id(&self, other: &EmissionsGroup, id: &Symbol) -> bool {
+        match (self.emissions_map.get(id), other.emissions_map.get(id)) {
+            (Some(mine), Some(theirs)) => mine.matches(theirs),
+            _ => false, // if at least one is None, it is not a match
+        }
+    }
+
     pub fn matches_on_ids(
         &self,
         other: &EmissionsGroup,
-        on_ids: Option<&BTreeSet<&String>>,
+        on_ids: Option<&BTreeSet<Symbol>>,
         exact: bool,
     ) -> bool {
-        let on_ids: BTreeSet<_> = match on_ids {
-            Some(ids) => ids.iter().cloned().collect::<BTreeSet<&String>>(),
-            None => {
-                let me = self.emission_ids().collect::<BTreeSet<&String>>();
-                let them = other.emission_ids().collect::<BTreeSet<&String>>();
-                me.union(&them).cloned().collect::<BTreeSet<&String>>()
-            }
+        // Check if the emissions in `self` and `other` match on the specified IDs (or on
+        // every ID either of them has)
+        let matches_on = match on_ids {
+            Some(ids) => ids.iter().all(|id| self.matches_on_id(other, id)),
+            None => self
+                .emission_ids()
+                .chain(other.emission_ids())
+                .all(|id| self.matches_on_id(other, id)),
         };
 
-        // Check if the emissions in `self` and `other` match on the specified IDs
-        let matches_on = on_ids.iter().all(|id| {
-            let mine = self.emissions_map.get(*id);
-            let theirs = other.emissions_map.get(*id);
-            match (mine, theirs) {
-                (Some(mine), Some(theirs)) => mine.value() == theirs.value(),
-                _ => false, // if at least one is None, it is not a match
-            }
-        });
-
         if !matches_on {
             return false;
         }
 
-        if exact {
+        if let (true, Some(on_ids)) = (exact, on_ids) {
             // For all ids present in both groups but not in on_ids, ensure the values do NOT match
-            let self_ids = self.emission_ids().collect::<BTreeSet<&String>>();
-            let other_ids = other.emission_ids().collect::<BTreeSet<&String>>();
-            let common_ids = self_ids
-                .intersection(&other_ids)
-                .filter(|id| !on_ids.contains(*id))
-                .cloned()
-                .collect::<BTreeSet<&String>>();
-            for id in common_ids {
-                let mine = self.emissions_map.get(id);
-                let theirs = other.emissions_map.get(id);
-                if let (Some(mine), Some(theirs)) = (mine, theirs) {
-                    if mine.value() == theirs.value() {
-                        return false;
-                    }
-                }
+            let matches_extra = self
+                .emissions_map
+                .iter()
+                .filter(|(id, _)| !on_ids.contains(*id))
+                .any(|(id, mine)| {
+                    other
+                        .emissions_map
+                        .get(id)
+                        .is_some_and(|theirs| mine.matches(theirs))
+                });
+            if matches_extra {
+                return false;
             }
         }
         true
@@ -131,10 +170,7 @@ mod tests {
     }
 
     fn make_emission(id: &str, value: &str) -> Emission {
-        Emission {
-            id: id.to_string(),
-            value: value.to_string(),
-        }
+        Emission::new(id, value)
     }
 
     fn make_group<'a>(subs: &'a DummySubmission, pairs: &[(&str, &str)]) -> EmissionsGroup<'a> {
@@ -153,7 +189,7 @@ mod tests {
         let g3 = make_group(&sub, &[("a", "1"), ("b", "DIFF"), ("c", "3")]);
         let g4 = make_group(&sub, &[("a", "1"), ("b", "2"), ("c", "3")]);
 
-        let all_ids: Vec<&String> = [
+        let all_ids: Vec<&Symbol> = [
             g1.emissions_map(),
             g2.emissions_map(),
             g3.emissions_map(),
@@ -162,10 +198,10 @@ mod tests {
         .iter()
         .flat_map(|m| m.keys())
         .collect();
-        let on_ids: BTreeSet<&String> = all_ids
+        let on_ids: BTreeSet<Symbol> = all_ids
             .iter()
             .filter(|id| id.as_str() == "a" || id.as_str() == "b")
-            .map(|id| *id)
+            .map(|id| **id)
             .collect();
 
         // g1 and g2 match on a, b (non-exact)
@@ -184,7 +220,7 @@ mod tests {
         let g3 = make_group(&sub, &[("a", "1"), ("b", "2"), ("c", "3")]);
         let g4 = make_group(&sub, &[("a", "1"), ("b", "2"), ("d", "3")]);
 
-        let all_ids: Vec<&String> = [
+        let all_ids: Vec<&Symbol> = [
             g1.emissions_map(),
             g2.emissions_map(),
             g3.emissions_map(),
@@ -193,10 +229,10 @@ mod tests {
         .iter()
         .flat_map(|m| m.keys())
         .collect();
-        let on_ids: BTreeSet<&String> = all_ids
+        let on_ids: BTreeSet<Symbol> = all_ids
             .iter()
             .filter(|id| id.as_str() == "a" || id.as_str() == "b")
-            .map(|id| *id)
+            .map(|id| **id)
             .collect();
 
         // g1 and g2 match on a, b, but c differs, so exact
@@ -206,4 +242,84 @@ mod tests {
         // g1 and g4 match on a, b, and have no other common ids, so exact
         assert!(g1.matches_on_ids(&g4, Some(&on_ids), true));
     }
+
+    #[test]
+    fn test_matches_after_normalization() {
+        let sub = DummySubmission;
+        let mut g1 = make_group(&sub, &[("a", "Hello World "), ("b", "1.0000001")]);
+        let mut g2 = make_group(&sub, &[("a", "hello   world"), ("b", "1.0")]);
+        assert!(!g1.matches(&g2));
+
+        let config = NormalizationConfig::new(&[
+            crate::rufus::parse_normalization_spec("a=whitespace,casefold").unwrap(),
+            crate::rufus::parse_normalization_spec("b=numeric").unwrap(),
+        ]);
+        g1.normalize(&config);
+        g2.normalize(&config);
+        assert!(g1.matches(&g2));
+        // The raw values are kept for reporting
+        assert_eq!(
+            g1.emissions_map()[&Symbol::from("a")].value(),
+            "Hello World "
+        );
+    }
+
+    fn schema(yaml: &str) -> EmissionSchema {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_apply_schema_types_values() {
+        let sub = DummySubmission;
+        let schema = schema("a:\n  type: float\n  tolerance: 1.0e-3\nb:\n  type: integer\n");
+        let mut g1 = make_group(&sub, &[("a", "2.0001"), ("b", "not a number"), ("c", "x")]);
+        let mut g2 = make_group(&sub, &[("a", "2"), ("b", "not a number"), ("c", "x")]);
+        g1.apply_schema(&schema);
+        g2.apply_schema(&schema);
+
+        // Within the tolerance once typed, with the raw value kept for reporting
+        let a = &g1.emissions_map()[&Symbol::intern("a")];
+        assert!(a.typed().is_some());
+        assert_eq!(a.value(), "2.0001");
+        assert!(g1.matches(&g2));
+
+        // Values that don't parse are left untyped and compared as given
+        let b = &g1.emissions_map()[&Symbol::intern("b")];
+        assert!(b.typed().is_none());
+        assert_eq!(b.key(), b.value);
+        // IDs without an entry are untouched
+        assert!(g1.emissions_map()[&Symbol::intern("c")].typed().is_none());
+    }
+
+    #[test]
+    fn test_normalization_overrides_schema_tolerance() {
+        let sub = DummySubmission;
+        let schema = schema("a:\n  type: float\n  tolerance: 1.0e-6\n");
+        let build = |value: &str| {
+            let mut group = make_group(&sub, &[("a", value)]);
+            group.apply_schema(&schema);
+            group
+        };
+        let (mut g1, mut g2) = (build("2.01"), build("2.0"));
+        assert!(!g1.matches(&g2));
+
+        // An exact normalization keeps the schema's canonical form
+        g1.normalize(&NormalizationConfig::default());
+        assert!(!g1.matches(&g2));
+        assert_eq!(
+            g1.emissions_map()[&Symbol::intern("a")].key().as_str(),
+            "2.010000"
+        );
+
+        // A numeric normalization for the ID replaces the schema's tolerance
+        let config =
+            NormalizationConfig::new(&[
+                crate::rufus::parse_normalization_spec("a=numeric:0.1").unwrap()
+            ]);
+        g1.normalize(&config);
+        g2.normalize(&config);
+        assert!(g1.matches(&g2));
+        // The value stays typed for display
+        assert!(g1.emissions_map()[&Symbol::intern("a")].typed().is_some());
+    }
 }
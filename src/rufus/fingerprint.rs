@@ -0,0 +1,185 @@
+use std::{
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// How many tokens each hashed k-gram spans. Shorter matches than this are ignored.
+pub const K_GRAM: usize = 8;
+
+/// How many consecutive k-gram hashes winnowing picks one fingerprint from. Any match at
+/// least `K_GRAM + WINDOW - 1` tokens long is guaranteed to share a fingerprint.
+pub const WINDOW: usize = 4;
+
+/// Words kept as themselves when tokenizing, since they shape the code's structure. Every
+/// other identifier is replaced by a placeholder so renaming variables doesn't hide copying.
+const KEYWORDS: &[&str] = &[
+    "and", "break", "case", "catch", "class", "continue", "def", "do", "else", "elif", "elseif",
+    "end", "for", "function", "if", "import", "in", "lambda", "not", "or", "return", "switch",
+    "try", "while", "with",
+];
+
+/// Splits source code into tokens, normalized so that cosmetic edits don't change them:
+/// comments and whitespace are dropped, identifiers become `I`, numbers `N`, and strings `S`.
+/// Keywords and punctuation are kept as-is. This is deliberately language-agnostic.
+pub fn tokenize(source: &str) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' || (c == '/' && next == Some('/')) {
+            // Line comment
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            // Block comment
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' {
+            // String literal, ending at the closing quote or the end of the line
+            i += 1;
+            while i < chars.len() && chars[i] != c && chars[i] != '\n' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+            tokens.push("S".to_string());
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push("N".to_string());
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if KEYWORDS.contains(&word.as_str()) {
+                tokens.push(word);
+            } else {
+                tokens.push("I".to_string());
+            }
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// The winnowed k-gram hashes of some source code (as in MOSS), which two copies of the same
+/// code share even after variables are renamed, comments edited, or code reformatted.
+#[derive(Debug, Clone, Default)]
+pub struct Fingerprint {
+    hashes: HashSet<u64>,
+}
+
+impl Fingerprint {
+    pub fn new(source: &str) -> Self {
+        Fingerprint::with_params(source, K_GRAM, WINDOW)
+    }
+
+    pub fn with_params(source: &str, k: usize, window: usize) -> Self {
+        let tokens = tokenize(source);
+        let grams: Vec<u64> = tokens
+            .windows(k.min(tokens.len()).max(1))
+            .map(|gram| {
+                let mut hasher = DefaultHasher::new();
+                gram.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+
+        // Keep the smallest hash in every window (the rightmost one on ties)
+        let hashes = grams
+            .windows(window.min(grams.len()).max(1))
+            .filter_map(|w| w.iter().rev().min().copied())
+            .collect();
+        Fingerprint { hashes }
+    }
+
+    /// Adds the fingerprints of another file from the same submission.
+    pub fn extend(&mut self, other: Fingerprint) {
+        self.hashes.extend(other.hashes);
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// The fraction of the smaller fingerprint that's also in the other, so that copying part
+    /// of a longer submission still scores high.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let smaller = self.len().min(other.len());
+        if smaller == 0 {
+            return 0.0;
+        }
+        self.hashes.intersection(&other.hashes).count() as f64 / smaller as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = r#"
+import numpy as np
+
+def solve(A, b):
+    # Solve by elimination
+    x = np.linalg.solve(A, b)
+    residual = np.linalg.norm(A @ x - b)
+    if residual > 1e-8:
+        print("inaccurate")
+    return x, residual
+"#;
+
+    const DISGUISED: &str = r#"
+import numpy as np
+def answer(M, rhs):
+    sol = np.linalg.solve(M, rhs)  # the answer
+    err = np.linalg.norm(M @ sol - rhs)
+    if err > 1e-5:
+        print('not accurate enough')
+    return sol, err
+"#;
+
+    const DIFFERENT: &str = r#"
+def answer(M):
+    total = 0
+    for row in M:
+        for value in row:
+            total += value * value
+    return total ** 0.5
+"#;
+
+    #[test]
+    fn test_tokenize_normalizes() {
+        assert_eq!(
+            tokenize("if x1 > 2.5: # check\n    y = \"a\\\"b\""),
+            vec!["if", "I", ">", "N", ":", "I", "=", "S"]
+        );
+        assert_eq!(tokenize("/* a */ f(x) // b"), vec!["I", "(", "I", ")"]);
+    }
+
+    #[test]
+    fn test_similarity_survives_renaming() {
+        let original = Fingerprint::new(ORIGINAL);
+        assert!(!original.is_empty());
+        assert_eq!(original.similarity(&original), 1.0);
+        assert_eq!(original.similarity(&Fingerprint::new(DISGUISED)), 1.0);
+        assert!(original.similarity(&Fingerprint::new(DIFFERENT)) < 0.2);
+        assert_eq!(original.similarity(&Fingerprint::default()), 0.0);
+    }
+}
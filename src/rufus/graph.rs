@@ -0,0 +1,335 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use itertools::Itertools;
+
+use crate::rufus::{Grouping, Symbol};
+
+/// Node colors for sections, in order of the sections' names. Nodes without a section are
+/// gray, and the colors repeat if there are more sections than this.
+const SECTION_COLORS: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#bcbd22",
+    "#17becf",
+];
+const NO_SECTION_COLOR: &str = "#bbbbbb";
+
+/// A student in the collusion graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    /// The submitter's key (see `Submitter::key`)
+    pub id: String,
+    pub name: String,
+    pub sid: Option<String>,
+    pub section: Option<String>,
+}
+
+impl GraphNode {
+    /// The name and SID, e.g. `Student A (10000001)`.
+    pub fn label(&self) -> String {
+        match &self.sid {
+            Some(sid) => format!("{} ({})", self.name, sid),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// A connection between two students who were in at least one group together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// The emission IDs the two shared values for, across every group they're both in
+    pub shared: BTreeSet<Symbol>,
+    /// `-log10` of the lowest chance of coincidence among those groups, so rarer matches
+    /// weigh more
+    pub rarity: f64,
+}
+
+impl GraphEdge {
+    /// How many emissions the two shared, used as the edge's weight.
+    pub fn weight(&self) -> usize {
+        self.shared.len()
+    }
+
+    /// The shared emission IDs, comma-separated in alphabetical order.
+    pub fn shared_ids(&self) -> String {
+        self.shared.iter().map(|id| id.as_str()).sorted().join(",")
+    }
+}
+
+/// Who is connected to whom: every submitter in a group is a node, with an edge between each
+/// pair of submitters that were in a group together (co-submitters of the same submission
+/// aren't linked, since they were allowed to work together).
+#[derive(Debug, Clone, Default)]
+pub struct CollusionGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl CollusionGraph {
+    /// Builds the graph for `groups`, looking up each submitter's section in `sections` by
+    /// their key.
+    pub fn new(groups: &[&Grouping], sections: &HashMap<String, String>) -> Self {
+        let mut nodes = BTreeMap::new();
+        let mut edges: BTreeMap<(String, String), GraphEdge> = BTreeMap::new();
+        for grouping in groups {
+            let members: Vec<Vec<String>> = grouping
+                .groups()
+                .iter()
+                .map(|g| {
+                    g.submission()
+                        .submitters()
+                        .iter()
+                        .map(|s| {
+                            nodes.entry(s.key().to_string()).or_insert(GraphNode {
+                                id: s.key().to_string(),
+                                name: s.name.clone(),
+                                sid: s.sid.clone(),
+                                section: sections.get(s.key()).cloned(),
+                            });
+                            s.key().to_string()
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let rarity = (-grouping.p_value().log10()).max(0.0);
+            for (a, b) in members.iter().tuple_combinations() {
+                for (a, b) in a.iter().cartesian_product(b) {
+                    if a == b {
+                        continue;
+                    }
+                    let (source, target) = if a < b { (a, b) } else { (b, a) };
+                    let edge = edges
+                        .entry((source.clone(), target.clone()))
+                        .or_insert(GraphEdge {
+                            source: source.clone(),
+                            target: target.clone(),
+                            shared: BTreeSet::new(),
+                            rarity: 0.0,
+                        });
+                    edge.shared.extend(grouping.on_ids());
+                    edge.rarity = edge.rarity.max(rarity);
+                }
+            }
+        }
+        CollusionGraph {
+            nodes: nodes.into_values().collect(),
+            edges: edges.into_values().collect(),
+        }
+    }
+
+    /// The color of each section, by section name.
+    fn section_colors(&self) -> BTreeMap<&str, &'static str> {
+        self.nodes
+            .iter()
+            .filter_map(|n| n.section.as_deref())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .zip(SECTION_COLORS.iter().copied().cycle())
+            .collect()
+    }
+
+    fn color(&self, node: &GraphNode, colors: &BTreeMap<&str, &'static str>) -> &'static str {
+        node.section
+            .as_deref()
+            .and_then(|s| colors.get(s).copied())
+            .unwrap_or(NO_SECTION_COLOR)
+    }
+
+    /// Renders the graph in Graphviz's DOT language. Edges are labelled with how many
+    /// emissions were shared and drawn thicker the rarer the match.
+    pub fn to_dot(&self) -> String {
+        let colors = self.section_colors();
+        // Writing to a String can't fail
+        let mut out = String::new();
+        let _ = writeln!(out, "graph rufus {{");
+        let _ = writeln!(out, "  node [shape=box, style=filled];");
+        for node in &self.nodes {
+            let section = node
+                .section
+                .as_ref()
+                .map(|s| format!(", section=\"{}\"", dot_escape(s)))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "  \"{}\" [label=\"{}\", fillcolor=\"{}\"{}];",
+                dot_escape(&node.id),
+                dot_escape(&node.label()),
+                self.color(node, &colors),
+                section
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "  \"{}\" -- \"{}\" [weight={}, label=\"{}\", penwidth={:.2}, rarity={:.3}, shared=\"{}\"];",
+                dot_escape(&edge.source),
+                dot_escape(&edge.target),
+                edge.weight(),
+                edge.weight(),
+                1.0 + edge.rarity.min(7.0),
+                edge.rarity,
+                dot_escape(&edge.shared_ids())
+            );
+        }
+        let _ = writeln!(out, "}}");
+        out
+    }
+
+    /// Renders the graph as GraphML (e.g. for Gephi), with the edge weight, rarity, and shared
+    /// emission IDs as edge attributes and the label, SID, section, and color as node ones.
+    pub fn to_graphml(&self) -> String {
+        let colors = self.section_colors();
+        let mut out = String::new();
+        let _ = writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        let _ = writeln!(
+            out,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+        );
+        for (name, target, kind) in [
+            ("label", "node", "string"),
+            ("sid", "node", "string"),
+            ("section", "node", "string"),
+            ("color", "node", "string"),
+            ("weight", "edge", "double"),
+            ("rarity", "edge", "double"),
+            ("shared", "edge", "string"),
+        ] {
+            let _ = writeln!(
+                out,
+                "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+                name, target, name, kind
+            );
+        }
+        let _ = writeln!(out, "  <graph id=\"rufus\" edgedefault=\"undirected\">");
+        for node in &self.nodes {
+            let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&node.id));
+            let _ = writeln!(
+                out,
+                "      <data key=\"label\">{}</data>",
+                xml_escape(&node.label())
+            );
+            if let Some(sid) = &node.sid {
+                let _ = writeln!(out, "      <data key=\"sid\">{}</data>", xml_escape(sid));
+            }
+            if let Some(section) = &node.section {
+                let _ = writeln!(
+                    out,
+                    "      <data key=\"section\">{}</data>",
+                    xml_escape(section)
+                );
+            }
+            let _ = writeln!(
+                out,
+                "      <data key=\"color\">{}</data>",
+                self.color(node, &colors)
+            );
+            let _ = writeln!(out, "    </node>");
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\">",
+                xml_escape(&edge.source),
+                xml_escape(&edge.target)
+            );
+            let _ = writeln!(out, "      <data key=\"weight\">{}</data>", edge.weight());
+            let _ = writeln!(out, "      <data key=\"rarity\">{:.3}</data>", edge.rarity);
+            let _ = writeln!(
+                out,
+                "      <data key=\"shared\">{}</data>",
+                xml_escape(&edge.shared_ids())
+            );
+            let _ = writeln!(out, "    </edge>");
+        }
+        let _ = writeln!(out, "  </graph>");
+        let _ = writeln!(out, "</graphml>");
+        out
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gradescope::types::Submitter,
+        rufus::{Emission, EmissionsGroup},
+        sources::SimpleSubmission,
+    };
+
+    fn submission(names: &[&str], values: &[(&str, &str)]) -> SimpleSubmission {
+        let submitter = |name: &str| Submitter {
+            name: name.to_string(),
+            sid: Some(format!("sid-{}", name)),
+            email: String::new(),
+        };
+        let mut submission = SimpleSubmission::new(submitter(names[0]));
+        submission
+            .submitters
+            .extend(names[1..].iter().map(|n| submitter(n)));
+        submission.emissions = values.iter().map(|(id, v)| Emission::new(id, v)).collect();
+        submission
+    }
+
+    #[test]
+    fn test_graph_links_group_members() {
+        let subs = [
+            submission(&["a"], &[("x", "1"), ("y", "2")]),
+            submission(&["b", "c"], &[("x", "1"), ("y", "2")]),
+            submission(&["d"], &[("x", "1"), ("y", "3")]),
+        ];
+        let groups: Vec<EmissionsGroup> = subs
+            .iter()
+            .map(|s| EmissionsGroup::from_submission(s))
+            .collect();
+        let x = Grouping::new([Symbol::intern("x")].into(), groups.iter().collect());
+        let xy = Grouping::new(
+            [Symbol::intern("x"), Symbol::intern("y")].into(),
+            groups[..2].iter().collect(),
+        );
+        let sections = HashMap::from([("sid-a".to_string(), "1".to_string())]);
+        let graph = CollusionGraph::new(&[&xy, &x], &sections);
+
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(graph.nodes[0].section.as_deref(), Some("1"));
+        // b and c submitted together, so they aren't linked to each other
+        let edges: Vec<_> = graph
+            .edges
+            .iter()
+            .map(|e| (e.source.as_str(), e.target.as_str(), e.weight()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("sid-a", "sid-b", 2),
+                ("sid-a", "sid-c", 2),
+                ("sid-a", "sid-d", 1),
+                ("sid-b", "sid-d", 1),
+                ("sid-c", "sid-d", 1),
+            ]
+        );
+
+        let dot = graph.to_dot();
+        assert!(
+            dot.contains("\"sid-a\" [label=\"a (sid-a)\", fillcolor=\"#1f77b4\", section=\"1\"];")
+        );
+        assert!(dot.contains("\"sid-a\" -- \"sid-b\" [weight=2"));
+        let graphml = graph.to_graphml();
+        assert_eq!(graphml.matches("<node ").count(), 4);
+        assert_eq!(graphml.matches("<edge ").count(), 5);
+    }
+}
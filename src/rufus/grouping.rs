@@ -1,43 +1,517 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use crate::rufus::EmissionsGroup;
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use itertools::Itertools;
 
+use crate::{
+    gradescope::types::{Score, Test},
+    rufus::{EmissionsGroup, GroupIdentity, Symbol, ValueFrequencies},
+};
+
+/// Submissions that emitted the same values for every ID in `on_ids`.
 pub struct Grouping<'a> {
-    on_ids: BTreeSet<&'a String>,
+    on_ids: BTreeSet<Symbol>,
     groups: Vec<&'a EmissionsGroup<'a>>,
+    /// The estimated chance the members match by coincidence (see
+    /// `ValueFrequencies::coincidence_probability`)
+    p_value: f64,
 }
 
 impl<'a> Grouping<'a> {
-    pub fn new(on_ids: BTreeSet<&'a String>, groups: Vec<&'a EmissionsGroup<'a>>) -> Self {
-        Grouping { on_ids, groups }
+    pub fn new(on_ids: BTreeSet<Symbol>, groups: Vec<&'a EmissionsGroup<'a>>) -> Self {
+        Grouping {
+            on_ids,
+            groups,
+            p_value: 1.0,
+        }
     }
 
-    pub fn on_ids(&self) -> &BTreeSet<&'a String> {
+    pub fn on_ids(&self) -> &BTreeSet<Symbol> {
         &self.on_ids
     }
 
-    pub fn groups(&self) -> &Vec<&'a EmissionsGroup<'a>> {
-        &self.groups
+    /// The IDs in `on_ids` in alphabetical order, for display.
+    pub fn sorted_on_ids(&self) -> Vec<Symbol> {
+        self.on_ids
+            .iter()
+            .copied()
+            .sorted_by_key(|id| id.as_str())
+            .collect()
     }
 
-    pub fn add_group(&mut self, group: &'a EmissionsGroup<'a>) {
-        self.groups.push(group);
+    pub fn groups(&self) -> &Vec<&'a EmissionsGroup<'a>> {
+        &self.groups
     }
 
     pub fn len(&self) -> usize {
         self.groups.len()
     }
 
-    pub fn matches_group_on_ids(
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// The values the members share on `on_ids`.
+    pub fn shared_values(&self) -> Vec<(Symbol, Symbol)> {
+        let Some(first) = self.groups.first() else {
+            return vec![];
+        };
+        self.on_ids
+            .iter()
+            .filter_map(|id| Some((*id, first.emissions_map().get(id)?.key())))
+            .collect()
+    }
+
+    /// The group's identity across runs: its members and the values they share.
+    pub fn identity(&self) -> GroupIdentity {
+        GroupIdentity::new(
+            self.groups
+                .iter()
+                .flat_map(|g| g.submission().submitters())
+                .map(|s| s.key().to_string()),
+            self.shared_values()
+                .into_iter()
+                .map(|(id, value)| (id.to_string(), value.to_string())),
+        )
+    }
+
+    /// Every ID the members all emitted the same value for, including ones outside `on_ids`.
+    pub fn matched_ids(&self) -> BTreeSet<Symbol> {
+        let Some((first, rest)) = self.groups.split_first() else {
+            return BTreeSet::new();
+        };
+        first
+            .emissions()
+            .filter(|e| {
+                rest.iter()
+                    .all(|g| g.emissions_map().get(&e.id()).is_some_and(|o| o.matches(e)))
+            })
+            .map(|e| e.id())
+            .collect()
+    }
+
+    /// When each member submitted, in order, or `None` if any member has no parseable
+    /// timestamp (e.g. CSV and log sources), since timing a partial group would be misleading.
+    pub fn submission_times(&self) -> Option<Vec<DateTime<FixedOffset>>> {
+        self.groups
+            .iter()
+            .map(|g| g.submission().submitted_at())
+            .collect::<Option<Vec<_>>>()
+            .map(|times| times.into_iter().sorted().collect())
+    }
+
+    pub fn earliest(&self) -> Option<DateTime<FixedOffset>> {
+        self.submission_times()?.first().copied()
+    }
+
+    pub fn latest(&self) -> Option<DateTime<FixedOffset>> {
+        self.submission_times()?.last().copied()
+    }
+
+    /// The time between the first and last members' submissions.
+    pub fn time_spread(&self) -> Option<TimeDelta> {
+        Some(self.latest()? - self.earliest()?)
+    }
+
+    /// Whether every member submitted (with a parseable timestamp) within the bounds.
+    pub fn submitted_between(
         &self,
-        group: &EmissionsGroup<'a>,
-        on_ids: Option<&BTreeSet<&String>>,
-        exact: bool,
+        after: Option<DateTime<FixedOffset>>,
+        before: Option<DateTime<FixedOffset>>,
     ) -> bool {
-        on_ids.is_none_or(|on_ids| on_ids.is_subset(&self.on_ids))
-            && self
-                .groups
-                .get(0)
-                .is_some_and(|g| group.matches_on_ids(g, on_ids, exact))
+        self.submission_times().is_some_and(|times| {
+            times.iter().all(|t| {
+                after.is_none_or(|after| *t >= after) && before.is_none_or(|before| *t <= before)
+            })
+        })
+    }
+
+    /// Each member's score.
+    pub fn scores(&self) -> Vec<Score> {
+        self.groups
+            .iter()
+            .map(|g| *g.submission().score())
+            .collect()
+    }
+
+    /// Each member's parent tests for the IDs in `on_ids`, once each (several emissions can
+    /// share a parent test), or `None` for members missing a parent test for any of them.
+    fn related_tests(&self) -> Vec<Option<Vec<&Test>>> {
+        self.groups
+            .iter()
+            .map(|g| {
+                self.on_ids
+                    .iter()
+                    .map(|id| g.submission().parent_test(id.as_str()))
+                    .collect::<Option<Vec<_>>>()
+                    .map(|tests| tests.into_iter().unique_by(|t| &t.number).collect())
+            })
+            .collect()
+    }
+
+    /// Each member's total score and maximum on the parent tests of `on_ids`, or `None` for
+    /// members missing a parent test or its scores.
+    pub fn related_scores(&self) -> Vec<Option<(Score, Score)>> {
+        self.related_tests()
+            .into_iter()
+            .map(|tests| {
+                tests?.iter().try_fold((0.0, 0.0), |(score, max), t| {
+                    Some((score + t.score?, max + t.max_score?))
+                })
+            })
+            .collect()
+    }
+
+    /// Whether every member passed the parent tests of every ID in `on_ids`, in which case
+    /// matching values are probably just correct answers. Groups whose emissions have no
+    /// parent tests can't be told apart from copying, so they never count.
+    pub fn all_full_marks(&self) -> bool {
+        !self.groups.is_empty()
+            && self.related_tests().iter().all(|tests| {
+                tests
+                    .as_ref()
+                    .is_some_and(|tests| tests.iter().all(|t| !t.is_incorrect()))
+            })
+    }
+
+    /// The score and maximum every member got on the parent tests of `on_ids`, if they're
+    /// identical and short of full marks, and the members emitted identical wrong values: the
+    /// same mistakes plus the same values is a stronger sign of copying than the same values
+    /// alone.
+    pub fn identical_partial_scores(&self) -> Option<(Score, Score)> {
+        if self.groups.len() < 2 {
+            return None;
+        }
+        let scores = self.related_scores();
+        let (score, max) = (*scores.first()?)?;
+        if score >= max || !scores.iter().all_equal() {
+            return None;
+        }
+
+        // Raw values, since normalized ones can match without being the same mistake
+        let wrong = self.shared_incorrect_ids();
+        let identical_wrong_values = !wrong.is_empty()
+            && wrong.iter().all(|id| {
+                self.groups
+                    .iter()
+                    .map(|g| g.emissions_map().get(id).map(|e| e.value()))
+                    .all_equal()
+            });
+        identical_wrong_values.then_some((score, max))
+    }
+
+    /// Each member's parent test for the emission with ID `id`, if it has one.
+    pub fn parent_tests(&self, id: Symbol) -> Vec<Option<&Test>> {
+        self.groups
+            .iter()
+            .map(|g| g.submission().parent_test(id.as_str()))
+            .collect()
+    }
+
+    /// The IDs in `on_ids` whose parent test every member got wrong. Sharing a wrong answer is
+    /// much harder to explain away than sharing a right one.
+    pub fn shared_incorrect_ids(&self) -> Vec<Symbol> {
+        self.sorted_on_ids()
+            .into_iter()
+            .filter(|id| {
+                self.parent_tests(*id)
+                    .iter()
+                    .all(|t| t.is_some_and(Test::is_incorrect))
+            })
+            .collect()
+    }
+
+    /// How common the shared values are across `population`: for each ID in `on_ids`, the
+    /// number of submissions with the group's value and the number that emitted the ID at all.
+    pub fn value_counts(&self, population: &[EmissionsGroup]) -> BTreeMap<Symbol, (usize, usize)> {
+        let Some(first) = self.groups.first() else {
+            return BTreeMap::new();
+        };
+        self.on_ids
+            .iter()
+            .filter_map(|id| {
+                let shared = first.emissions_map().get(id)?;
+                let emitted: Vec<_> = population
+                    .iter()
+                    .filter_map(|g| g.emissions_map().get(id))
+                    .collect();
+                let same = emitted.iter().filter(|e| e.matches(shared)).count();
+                Some((*id, (same, emitted.len())))
+            })
+            .collect()
+    }
+}
+
+/// Finds every group of submissions with identical values on some `k` emission IDs.
+///
+/// For each combination of `k` IDs, the submissions that emitted all of them are partitioned
+/// by their values on those IDs, so every pair in a grouping matches on `on_ids` and the
+/// result doesn't depend on the order of `groups`. With `exact`, members that also match
+/// another member of their grouping on an ID outside `on_ids` are left out, since they'll be
+/// grouped together on the larger set of IDs.
+///
+/// Each grouping's p-value is estimated from how common its values are across `groups`.
+pub fn hunt<'a>(groups: &'a [EmissionsGroup<'a>], k: usize, exact: bool) -> Vec<Grouping<'a>> {
+    // Alphabetical, so the output order doesn't depend on the order IDs were interned in
+    let all_emission_ids: Vec<Symbol> = groups
+        .iter()
+        .flat_map(|g| g.emission_ids().copied())
+        .unique()
+        .sorted_by_key(|id| id.as_str())
+        .collect();
+
+    let frequencies = ValueFrequencies::new(groups);
+    let mut groupings: Vec<Grouping> = vec![];
+    for ids in all_emission_ids.into_iter().combinations(k) {
+        // Keyed by the values on `ids`, in the same (alphabetical) order as the IDs
+        let mut by_values: HashMap<Vec<Symbol>, Vec<&'a EmissionsGroup<'a>>> = HashMap::new();
+        for group in groups {
+            let values = ids
+                .iter()
+                .map(|id| group.emissions_map().get(id).map(|e| e.key()))
+                .collect::<Option<Vec<Symbol>>>();
+            if let Some(values) = values {
+                by_values.entry(values).or_default().push(group);
+            }
+        }
+
+        // Ordered by the values' strings, so the output order is stable
+        let by_values = by_values.into_iter().sorted_by_cached_key(|(values, _)| {
+            values.iter().map(|v| v.as_str()).collect::<Vec<_>>()
+        });
+        let on_ids: BTreeSet<Symbol> = ids.into_iter().collect();
+        for (_, members) in by_values {
+            let members = if exact {
+                members
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, a)| {
+                        !members
+                            .iter()
+                            .enumerate()
+                            .any(|(j, b)| *i != j && !a.matches_on_ids(b, Some(&on_ids), true))
+                    })
+                    .map(|(_, g)| *g)
+                    .collect()
+            } else {
+                members
+            };
+            if !members.is_empty() {
+                let mut grouping = Grouping::new(on_ids.clone(), members);
+                grouping.p_value =
+                    frequencies.coincidence_probability(&grouping.shared_values(), grouping.len());
+                groupings.push(grouping);
+            }
+        }
+    }
+
+    groupings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gradescope::types::Submitter, rufus::Emission, sources::SimpleSubmission};
+    use proptest::prelude::*;
+
+    const IDS: [&str; 4] = ["a", "b", "c", "d"];
+
+    /// Submissions emitting a random subset of `IDS`, with few enough distinct values that
+    /// they collide often.
+    fn submissions() -> impl Strategy<Value = Vec<Vec<Option<u8>>>> {
+        prop::collection::vec(
+            prop::collection::vec(prop::option::weighted(0.85, 0u8..3), IDS.len()),
+            0..12,
+        )
+    }
+
+    fn build(values: &[Vec<Option<u8>>]) -> Vec<SimpleSubmission> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, values)| {
+                let mut submission = SimpleSubmission::new(Submitter {
+                    name: i.to_string(),
+                    sid: None,
+                    email: String::new(),
+                });
+                submission.emissions = IDS
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(id, v)| v.map(|v| Emission::new(id, &v.to_string())))
+                    .collect();
+                submission
+            })
+            .collect()
+    }
+
+    fn name(group: &EmissionsGroup) -> String {
+        group.submission().submitters()[0].name.clone()
+    }
+
+    /// Groupings as (IDs, member names), ignoring the order they were found in.
+    fn normalized(groupings: &[Grouping]) -> BTreeSet<(Vec<Symbol>, BTreeSet<String>)> {
+        groupings
+            .iter()
+            .map(|g| {
+                (
+                    g.on_ids().iter().copied().collect(),
+                    g.groups().iter().map(|m| name(m)).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_times_and_matched_ids() {
+        let mut subs = build(&[
+            vec![Some(0), Some(1), Some(2), None],
+            vec![Some(0), Some(1), Some(0), None],
+            vec![Some(0), Some(1), Some(2), None],
+        ]);
+        subs[0].created_at = "2024-09-10 21:15:00.000000000 -04:00".to_string();
+        subs[1].created_at = "2024-09-10 20:45:00.000000000 -05:00".to_string();
+        let groups: Vec<EmissionsGroup> = subs
+            .iter()
+            .map(|s| EmissionsGroup::from_submission(s))
+            .collect();
+        let grouping = Grouping::new([Symbol::intern("a")].into(), groups.iter().collect());
+
+        assert_eq!(
+            grouping.matched_ids(),
+            [Symbol::intern("a"), Symbol::intern("b")].into()
+        );
+        // The third submission has no timestamp, so the group can't be timed
+        assert_eq!(grouping.submission_times(), None);
+        assert_eq!(grouping.earliest(), None);
+        assert_eq!(grouping.time_spread(), None);
+        assert!(!grouping.submitted_between(None, None));
+
+        // Offsets are taken into account
+        let pair = Grouping::new([Symbol::intern("a")].into(), groups[..2].iter().collect());
+        assert_eq!(pair.submission_times().unwrap().len(), 2);
+        assert_eq!(
+            pair.earliest().unwrap().to_rfc3339(),
+            "2024-09-10T21:15:00-04:00"
+        );
+        assert_eq!(pair.time_spread(), Some(TimeDelta::minutes(30)));
+        let time = |s: &str| DateTime::parse_from_rfc3339(s).ok();
+        assert!(pair.submitted_between(time("2024-09-10T21:15:00-04:00"), None));
+        assert!(!pair.submitted_between(None, time("2024-09-10T21:30:00-04:00")));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_members_match_on_ids(values in submissions(), k in 1usize..=3, exact: bool) {
+            let subs = build(&values);
+            let groups: Vec<EmissionsGroup> =
+                subs.iter().map(|s| EmissionsGroup::from_submission(s)).collect();
+
+            for grouping in hunt(&groups, k, exact) {
+                prop_assert_eq!(grouping.on_ids().len(), k);
+                for (a, b) in grouping.groups().iter().tuple_combinations() {
+                    prop_assert!(a.matches_on_ids(b, Some(grouping.on_ids()), false));
+                    if exact {
+                        // No pair also matches on an ID outside `on_ids`
+                        prop_assert!(a.matches_on_ids(b, Some(grouping.on_ids()), true));
+                    }
+                }
+                // Even singletons have every ID they were grouped on
+                for member in grouping.groups() {
+                    prop_assert!(grouping
+                        .on_ids()
+                        .iter()
+                        .all(|id| member.emissions_map().contains_key(id)));
+                }
+            }
+        }
+
+        #[test]
+        fn prop_no_qualifying_submission_is_missing(
+            values in submissions(),
+            k in 1usize..=3,
+            exact: bool,
+        ) {
+            let subs = build(&values);
+            let groups: Vec<EmissionsGroup> =
+                subs.iter().map(|s| EmissionsGroup::from_submission(s)).collect();
+            let groupings = hunt(&groups, k, exact);
+
+            for grouping in &groupings {
+                let on_ids = grouping.on_ids();
+                let first = grouping.groups()[0];
+                let members: BTreeSet<String> = grouping.groups().iter().map(|m| name(m)).collect();
+
+                for (i, group) in groups.iter().enumerate() {
+                    let same_values: Vec<&EmissionsGroup> = groups
+                        .iter()
+                        .enumerate()
+                        .filter(|(j, other)| *j != i && group.matches_on_ids(other, Some(on_ids), false))
+                        .map(|(_, other)| other)
+                        .collect();
+                    let qualifies = group.matches_on_ids(first, Some(on_ids), false)
+                        && (!exact
+                            || same_values
+                                .iter()
+                                .all(|other| group.matches_on_ids(other, Some(on_ids), true)));
+                    prop_assert_eq!(qualifies, members.contains(&name(group)));
+                }
+            }
+
+            // A submission is in at most one grouping per set of IDs
+            for (a, b) in groupings.iter().tuple_combinations() {
+                if a.on_ids() == b.on_ids() {
+                    let a_members: BTreeSet<String> = a.groups().iter().map(|m| name(m)).collect();
+                    prop_assert!(b.groups().iter().all(|m| !a_members.contains(&name(m))));
+                }
+            }
+        }
+
+        #[test]
+        fn prop_value_counts_include_members(values in submissions(), k in 1usize..=3) {
+            let subs = build(&values);
+            let groups: Vec<EmissionsGroup> =
+                subs.iter().map(|s| EmissionsGroup::from_submission(s)).collect();
+
+            for grouping in hunt(&groups, k, false) {
+                let counts = grouping.value_counts(&groups);
+                prop_assert_eq!(counts.keys().collect::<BTreeSet<_>>(), grouping.on_ids().iter().collect());
+                for (same, emitted) in counts.values() {
+                    prop_assert!(*same >= grouping.len());
+                    prop_assert!(emitted >= same);
+                    if k == 1 {
+                        // Partitioned by value, so everyone with the value is in the grouping
+                        prop_assert_eq!(*same, grouping.len());
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn prop_independent_of_input_order(
+            values in submissions(),
+            k in 1usize..=3,
+            exact: bool,
+            rotation in 0usize..12,
+        ) {
+            let subs = build(&values);
+            let groups: Vec<EmissionsGroup> =
+                subs.iter().map(|s| EmissionsGroup::from_submission(s)).collect();
+            let mut reordered = groups.clone();
+            reordered.reverse();
+            if !reordered.is_empty() {
+                let len = reordered.len();
+                reordered.rotate_left(rotation % len);
+            }
+
+            prop_assert_eq!(
+                normalized(&hunt(&groups, k, exact)),
+                normalized(&hunt(&reordered, k, exact))
+            );
+        }
     }
 }
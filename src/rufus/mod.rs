@@ -1,8 +1,36 @@
 #![allow(dead_code)] // this is a library, so we don't need to worry about dead code
+mod anonymize;
+mod coverage;
+mod diagnosis;
 mod emission;
 mod emission_group;
+mod fingerprint;
+mod graph;
 mod grouping;
+mod normalization;
+mod review;
+mod roster;
+mod schema;
+mod seed;
+mod significance;
+mod signing;
+mod symbol;
+mod value;
 
+pub use self::anonymize::*;
+pub use self::coverage::*;
+pub use self::diagnosis::*;
 pub use self::emission::*;
 pub use self::emission_group::*;
+pub use self::fingerprint::*;
+pub use self::graph::*;
 pub use self::grouping::*;
+pub use self::normalization::*;
+pub use self::review::*;
+pub use self::roster::*;
+pub use self::schema::*;
+pub use self::seed::*;
+pub use self::significance::*;
+pub use self::signing::*;
+pub use self::symbol::*;
+pub use self::value::*;
@@ -0,0 +1,312 @@
+use std::{collections::HashMap, str::FromStr};
+
+use itertools::Itertools;
+
+use super::{EmissionValue, ValueType};
+
+/// A single step in normalizing an emission value before it is compared.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NormalizationMode {
+    /// Strip leading/trailing whitespace from the value and from every line
+    Trim,
+    /// Collapse every run of whitespace (including newlines) into a single space
+    CollapseWhitespace,
+    /// Compare case-insensitively
+    CaseFold,
+    /// Treat the value as an unordered list (comma or whitespace separated)
+    SortedList,
+    /// Treat the value as a number, rounded to the nearest multiple of the given tolerance
+    Numeric(f64),
+    /// Treat the value as a vector/matrix, rounding each entry to the nearest multiple of the
+    /// given tolerance
+    Matrix(f64),
+}
+
+/// The tolerance used by `numeric`/`matrix` when none is given.
+pub const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+impl FromStr for NormalizationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        let tolerance = || match arg {
+            Some(arg) => match arg.parse::<f64>() {
+                Ok(tol) if tol > 0.0 => Ok(tol),
+                _ => Err(format!("Invalid tolerance \"{}\" (must be positive)", arg)),
+            },
+            None => Ok(DEFAULT_TOLERANCE),
+        };
+
+        match name.trim().to_lowercase().as_str() {
+            "trim" => Ok(NormalizationMode::Trim),
+            "whitespace" | "ws" => Ok(NormalizationMode::CollapseWhitespace),
+            "casefold" | "case" => Ok(NormalizationMode::CaseFold),
+            "sorted-list" | "sorted" => Ok(NormalizationMode::SortedList),
+            "numeric" | "number" => tolerance().map(NormalizationMode::Numeric),
+            "matrix" | "vector" => tolerance().map(NormalizationMode::Matrix),
+            other => Err(format!(
+                "Unknown normalization mode \"{}\" (expected trim, whitespace, casefold, sorted-list, numeric[:tol] or matrix[:tol])",
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for NormalizationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NormalizationMode::Trim => write!(f, "trim"),
+            NormalizationMode::CollapseWhitespace => write!(f, "whitespace"),
+            NormalizationMode::CaseFold => write!(f, "casefold"),
+            NormalizationMode::SortedList => write!(f, "sorted-list"),
+            NormalizationMode::Numeric(tol) => write!(f, "numeric:{}", tol),
+            NormalizationMode::Matrix(tol) => write!(f, "matrix:{}", tol),
+        }
+    }
+}
+
+impl NormalizationMode {
+    /// Applies this step to a value. Numeric modes leave values that don't parse untouched.
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            NormalizationMode::Trim => value
+                .trim()
+                .lines()
+                .map(|line| line.trim())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            NormalizationMode::CollapseWhitespace => {
+                value.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+            NormalizationMode::CaseFold => value.to_lowercase(),
+            NormalizationMode::SortedList => {
+                let inner = value
+                    .trim()
+                    .trim_start_matches(['[', '{', '('])
+                    .trim_end_matches([']', '}', ')']);
+                let mut items: Vec<&str> = if inner.contains(',') {
+                    inner.split(',').map(|i| i.trim()).collect()
+                } else {
+                    inner.split_whitespace().collect()
+                };
+                items.retain(|i| !i.is_empty());
+                items.sort_unstable();
+                items.join(", ")
+            }
+            NormalizationMode::Numeric(tol) => {
+                match EmissionValue::parse(value, ValueType::Float) {
+                    Ok(x) => x.canonical(Some(*tol)),
+                    Err(_) => value.to_string(),
+                }
+            }
+            NormalizationMode::Matrix(tol) => {
+                match EmissionValue::parse(value, ValueType::Matrix) {
+                    // Single rows compare the same as vectors
+                    Ok(EmissionValue::Matrix(rows)) if rows.len() == 1 => {
+                        EmissionValue::Vector(rows.concat()).canonical(Some(*tol))
+                    }
+                    Ok(m) => m.canonical(Some(*tol)),
+                    Err(_) => value.to_string(),
+                }
+            }
+        }
+    }
+}
+
+/// An ordered list of normalization steps for an emission ID. The default (no steps)
+/// compares values exactly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Normalization {
+    modes: Vec<NormalizationMode>,
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    /// Parses a comma-separated list of modes, e.g. `trim,casefold` or `matrix:1e-4`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let modes = s
+            .split(',')
+            .filter(|m| !m.trim().is_empty())
+            .map(NormalizationMode::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Normalization { modes })
+    }
+}
+
+/// Formats the modes as they're given on the command line, e.g. `trim,matrix:0.0001`.
+impl std::fmt::Display for Normalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.modes.iter().join(","))
+    }
+}
+
+impl Normalization {
+    pub fn new(modes: Vec<NormalizationMode>) -> Self {
+        Normalization { modes }
+    }
+
+    pub fn modes(&self) -> &Vec<NormalizationMode> {
+        &self.modes
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.modes.is_empty()
+    }
+
+    /// Returns the canonical form of `value`, or `None` when no normalization applies.
+    pub fn apply(&self, value: &str) -> Option<String> {
+        if self.is_exact() {
+            return None;
+        }
+        Some(
+            self.modes
+                .iter()
+                .fold(value.to_string(), |acc, mode| mode.apply(&acc)),
+        )
+    }
+}
+
+/// Which normalization to use for each emission ID, with a fallback for IDs that aren't
+/// listed.
+#[derive(Debug, Clone, Default)]
+pub struct NormalizationConfig {
+    default: Normalization,
+    per_id: HashMap<String, Normalization>,
+}
+
+/// The ID used in a normalization spec to set the default for every other ID.
+pub const WILDCARD_ID: &str = "*";
+
+impl NormalizationConfig {
+    /// Builds a config from `(id, normalization)` pairs, where an ID of `*` sets the
+    /// default.
+    pub fn new(specs: &[(String, Normalization)]) -> Self {
+        let mut config = NormalizationConfig::default();
+        for (id, normalization) in specs {
+            if id == WILDCARD_ID {
+                config.default = normalization.clone();
+            } else {
+                config.per_id.insert(id.clone(), normalization.clone());
+            }
+        }
+        config
+    }
+
+    pub fn for_id(&self, id: &str) -> &Normalization {
+        self.per_id.get(id).unwrap_or(&self.default)
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.default.is_exact() && self.per_id.values().all(|n| n.is_exact())
+    }
+}
+
+/// Parses an `ID=MODES` spec (as given on the command line).
+pub fn parse_normalization_spec(spec: &str) -> Result<(String, Normalization), String> {
+    let (id, modes) = spec
+        .rsplit_once('=')
+        .ok_or_else(|| format!("Expected ID=MODES, got \"{}\"", spec))?;
+    Ok((id.to_string(), modes.parse()?))
+}
+
+/// Rounds `x` to the nearest multiple of `tol` and prints it with matching precision.
+///
+/// This buckets values rather than comparing them within `tol`: values that round to the same
+/// multiple print identically, but two values straddling a bucket boundary (e.g. 0.000149 and
+/// 0.000151 with `tol` 0.0001) print differently however close they are. Grouping needs a
+/// single canonical value per submission, which a pairwise comparison can't give since it
+/// isn't transitive.
+pub fn format_rounded(x: f64, tol: f64) -> String {
+    let decimals = (-tol.log10().floor()).max(0.0) as usize;
+    let steps = (x / tol).round();
+    if steps == 0.0 {
+        return format!("{:.*}", decimals, 0.0);
+    }
+    format!("{:.*}", decimals, steps * tol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(modes: &str, value: &str) -> String {
+        modes
+            .parse::<Normalization>()
+            .unwrap()
+            .apply(value)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_text_modes() {
+        assert_eq!(normalize("trim", "  a b  \n c \n"), "a b\nc");
+        assert_eq!(normalize("whitespace", "a   b\n\tc"), "a b c");
+        assert_eq!(normalize("trim,casefold", " Hello "), "hello");
+        assert_eq!(normalize("sorted-list", "{c, a, b}"), "a, b, c");
+        assert_eq!(normalize("sorted-list", "3 1 2"), "1, 2, 3");
+    }
+
+    #[test]
+    fn test_numeric_modes() {
+        assert_eq!(
+            normalize("numeric", "1.0000001"),
+            normalize("numeric", "1.0")
+        );
+        assert_ne!(normalize("numeric", "1.00001"), normalize("numeric", "1.0"));
+        assert_eq!(normalize("numeric:0.01", "-0.001"), "0.00");
+        assert_eq!(normalize("numeric", "not a number"), "not a number");
+    }
+
+    #[test]
+    fn test_rounding_buckets_at_boundaries() {
+        let eps = 1e-6;
+        // 0.00015 is halfway between multiples of the tolerance, so values just either side
+        // of it land in different buckets
+        assert_eq!(format_rounded(0.00015 - eps, 0.0001), "0.0001");
+        assert_eq!(format_rounded(0.00015 + eps, 0.0001), "0.0002");
+        // ...while values almost a whole tolerance apart can share one
+        assert_eq!(format_rounded(0.00005 + eps, 0.0001), "0.0001");
+        assert_eq!(
+            normalize("numeric:0.0001", "0.000149"),
+            normalize("numeric:0.0001", "0.000051")
+        );
+        assert_ne!(
+            normalize("numeric:0.0001", "0.000149"),
+            normalize("numeric:0.0001", "0.000151")
+        );
+
+        assert_eq!(
+            normalize("matrix:0.1", "[[1, 2.02],\n [3, 4]]"),
+            normalize("matrix:0.1", "1 2\n3 4")
+        );
+        assert_eq!(normalize("matrix:1", "(1, 2, 3)"), "[1, 2, 3]");
+        assert_eq!(normalize("matrix:1", "1; 2"), "[[1], [2]]");
+    }
+
+    #[test]
+    fn test_config_falls_back_to_default() {
+        let config = NormalizationConfig::new(&[
+            parse_normalization_spec("*=trim").unwrap(),
+            parse_normalization_spec("det=numeric:0.5").unwrap(),
+        ]);
+        assert_eq!(
+            config.for_id("other").modes(),
+            &vec![NormalizationMode::Trim]
+        );
+        assert_eq!(
+            config.for_id("det").modes(),
+            &vec![NormalizationMode::Numeric(0.5)]
+        );
+        assert!(parse_normalization_spec("det=bogus").is_err());
+
+        // Printed the way it's given on the command line
+        let modes: Normalization = "ws,matrix:0.001".parse().unwrap();
+        assert_eq!(modes.to_string(), "whitespace,matrix:0.001");
+        assert_eq!(modes.to_string().parse::<Normalization>().unwrap(), modes);
+    }
+}
@@ -0,0 +1,251 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a group across runs by who is in it and what they matched on, so a decision
+/// about it still applies after the export is re-downloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GroupIdentity {
+    /// The members' submitter keys (SID, or email or name without one), sorted
+    pub members: Vec<String>,
+    /// The matched emission IDs and the values the members share
+    pub emissions: BTreeMap<String, String>,
+}
+
+impl GroupIdentity {
+    pub fn new<M, E>(members: M, emissions: E) -> Self
+    where
+        M: IntoIterator<Item = String>,
+        E: IntoIterator<Item = (String, String)>,
+    {
+        let mut members: Vec<String> = members.into_iter().collect();
+        members.sort();
+        members.dedup();
+        GroupIdentity {
+            members,
+            emissions: emissions.into_iter().collect(),
+        }
+    }
+}
+
+/// What a reviewer decided about a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Decision {
+    Confirmed,
+    Dismissed,
+    NeedsFollowUp,
+}
+
+impl std::fmt::Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Decision::Confirmed => "confirmed",
+            Decision::Dismissed => "dismissed",
+            Decision::NeedsFollowUp => "needs follow-up",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewEntry {
+    pub group: GroupIdentity,
+    pub decision: Decision,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+/// How a group found by a new run relates to the groups reviewed before.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReviewStatus<'a> {
+    /// Nothing like it was reviewed
+    New,
+    /// The same group was reviewed
+    Reviewed(&'a ReviewEntry),
+    /// A reviewed group on the same values has gained members
+    Grown {
+        previous: &'a ReviewEntry,
+        added: Vec<String>,
+    },
+    /// A reviewed group changed: the same members now match on different values, or some
+    /// members no longer match
+    Changed(&'a ReviewEntry),
+}
+
+impl ReviewStatus<'_> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReviewStatus::New => "new",
+            ReviewStatus::Reviewed(_) => "reviewed",
+            ReviewStatus::Grown { .. } => "grown",
+            ReviewStatus::Changed(_) => "changed",
+        }
+    }
+
+    pub fn previous(&self) -> Option<&ReviewEntry> {
+        match self {
+            ReviewStatus::New => None,
+            ReviewStatus::Reviewed(previous)
+            | ReviewStatus::Grown { previous, .. }
+            | ReviewStatus::Changed(previous) => Some(previous),
+        }
+    }
+}
+
+/// The decisions made about groups, saved as YAML so they can be shared and checked in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReviewState {
+    reviews: Vec<ReviewEntry>,
+}
+
+impl ReviewState {
+    /// Loads a review file, starting a new review if it doesn't exist yet.
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<ReviewState, String> {
+        if !path.as_ref().exists() {
+            return Ok(ReviewState::default());
+        }
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_yaml::from_str(&data).map_err(|e| e.to_string()))
+    }
+
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<(), String> {
+        let data = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, data).map_err(|e| e.to_string())
+    }
+
+    pub fn reviews(&self) -> &Vec<ReviewEntry> {
+        &self.reviews
+    }
+
+    pub fn get(&self, group: &GroupIdentity) -> Option<&ReviewEntry> {
+        self.reviews.iter().find(|r| &r.group == group)
+    }
+
+    /// Records a decision, keeping the group's existing note.
+    pub fn decide(&mut self, group: &GroupIdentity, decision: Decision) {
+        match self.reviews.iter_mut().find(|r| &r.group == group) {
+            Some(review) => review.decision = decision,
+            None => self.reviews.push(ReviewEntry {
+                group: group.clone(),
+                decision,
+                note: String::new(),
+            }),
+        }
+    }
+
+    /// Sets the note of a group that has a decision, returning whether it did.
+    pub fn set_note(&mut self, group: &GroupIdentity, note: &str) -> bool {
+        match self.reviews.iter_mut().find(|r| &r.group == group) {
+            Some(review) => {
+                review.note = note.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// How `group` relates to the reviewed groups. A group that grew is matched to the
+    /// largest reviewed group it contains.
+    pub fn status(&self, group: &GroupIdentity) -> ReviewStatus<'_> {
+        if let Some(review) = self.get(group) {
+            return ReviewStatus::Reviewed(review);
+        }
+
+        let is_subset = |a: &[String], b: &[String]| a.iter().all(|m| b.contains(m));
+        let grown_from = self
+            .reviews
+            .iter()
+            .filter(|r| r.group.emissions == group.emissions)
+            .filter(|r| is_subset(&r.group.members, &group.members))
+            .max_by_key(|r| r.group.members.len());
+        if let Some(previous) = grown_from {
+            let added = group
+                .members
+                .iter()
+                .filter(|m| !previous.group.members.contains(m))
+                .cloned()
+                .collect();
+            return ReviewStatus::Grown { previous, added };
+        }
+
+        let changed_from = self.reviews.iter().find(|r| {
+            r.group.members == group.members
+                || (r.group.emissions == group.emissions
+                    && is_subset(&group.members, &r.group.members))
+        });
+        match changed_from {
+            Some(previous) => ReviewStatus::Changed(previous),
+            None => ReviewStatus::New,
+        }
+    }
+
+    pub fn clear(&mut self, group: &GroupIdentity) {
+        self.reviews.retain(|r| &r.group != group);
+    }
+
+    pub fn len(&self) -> usize {
+        self.reviews.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reviews.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_state_round_trip() {
+        let group = GroupIdentity::new(
+            ["2".to_string(), "1".to_string()],
+            [("x".to_string(), "1".to_string())],
+        );
+        assert_eq!(group.members, vec!["1", "2"]);
+
+        let mut state = ReviewState::default();
+        assert!(!state.set_note(&group, "no decision yet"));
+        state.decide(&group, Decision::NeedsFollowUp);
+        assert!(state.set_note(&group, "ask about x"));
+        state.decide(&group, Decision::Confirmed);
+        assert_eq!(state.len(), 1);
+        assert_eq!(state.get(&group).unwrap().note, "ask about x");
+
+        let yaml = serde_yaml::to_string(&state).unwrap();
+        assert!(yaml.contains("decision: confirmed"));
+        assert_eq!(serde_yaml::from_str::<ReviewState>(&yaml).unwrap(), state);
+
+        state.clear(&group);
+        assert!(state.get(&group).is_none());
+    }
+
+    #[test]
+    fn test_status_of_rerun_groups() {
+        let identity = |members: &[&str], value: &str| {
+            GroupIdentity::new(
+                members.iter().map(|m| m.to_string()),
+                [("x".to_string(), value.to_string())],
+            )
+        };
+        let mut state = ReviewState::default();
+        state.decide(&identity(&["1", "2"], "a"), Decision::Dismissed);
+
+        assert_eq!(state.status(&identity(&["2", "1"], "a")).name(), "reviewed");
+        assert_eq!(state.status(&identity(&["3", "4"], "a")).name(), "new");
+        assert_eq!(state.status(&identity(&["1", "2"], "b")).name(), "changed");
+        assert_eq!(
+            state.status(&identity(&["1", "2", "3"], "a")),
+            ReviewStatus::Grown {
+                previous: &state.reviews()[0],
+                added: vec!["3".to_string()],
+            }
+        );
+
+        state.decide(&identity(&["5", "6", "7"], "c"), Decision::Confirmed);
+        assert_eq!(state.status(&identity(&["5", "6"], "c")).name(), "changed");
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// A student in the course roster.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RosterEntry {
+    pub sid: String,
+
+    #[serde(default)]
+    pub name: Option<String>,
+
+    #[serde(default)]
+    pub email: Option<String>,
+
+    #[serde(default)]
+    pub section: Option<String>,
+}
+
+/// The students in a course, loaded from a CSV with a `sid` column and optional `name`,
+/// `email`, and `section` columns.
+#[derive(Debug, Clone, Default)]
+pub struct Roster {
+    entries: Vec<RosterEntry>,
+}
+
+impl Roster {
+    pub fn new(entries: Vec<RosterEntry>) -> Self {
+        Roster { entries }
+    }
+
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Roster, String> {
+        let mut reader = csv::Reader::from_path(path).map_err(|e| e.to_string())?;
+        let entries = reader
+            .deserialize::<RosterEntry>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(Roster { entries })
+    }
+
+    pub fn entries(&self) -> &Vec<RosterEntry> {
+        &self.entries
+    }
+
+    pub fn get(&self, sid: &str) -> Option<&RosterEntry> {
+        self.entries.iter().find(|e| e.sid == sid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::{de::Error, Deserialize, Deserializer};
+
+use super::{ValueType, WILDCARD_ID};
+
+/// How an emission ID's values should be typed and compared.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchemaEntry {
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+
+    /// Numbers are rounded to the nearest multiple of this before comparing
+    #[serde(default, deserialize_with = "deserialize_tolerance")]
+    pub tolerance: Option<f64>,
+}
+
+/// Rejects tolerances that aren't finite and positive, which would round every number to
+/// infinity or NaN.
+fn deserialize_tolerance<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    match Option::<f64>::deserialize(deserializer)? {
+        Some(tolerance) if !(tolerance.is_finite() && tolerance > 0.0) => Err(D::Error::custom(
+            format!("tolerance must be a positive number, not {}", tolerance),
+        )),
+        tolerance => Ok(tolerance),
+    }
+}
+
+/// Maps emission IDs to the type of their values, loaded from a YAML file like:
+///
+/// ```yaml
+/// det_A:
+///   type: float
+///   tolerance: 1.0e-6
+/// A_inv:
+///   type: matrix
+///   tolerance: 1.0e-4
+/// "*":
+///   type: string
+/// ```
+///
+/// An ID of `*` applies to every emission ID that isn't listed.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct EmissionSchema {
+    entries: HashMap<String, SchemaEntry>,
+}
+
+impl EmissionSchema {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<EmissionSchema, String> {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_yaml::from_str(&data).map_err(|e| e.to_string()))
+    }
+
+    pub fn entry(&self, id: &str) -> Option<&SchemaEntry> {
+        self.entries
+            .get(id)
+            .or_else(|| self.entries.get(WILDCARD_ID))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_falls_back_to_wildcard() {
+        let path = std::env::temp_dir().join(format!("rufus-schema-{}.yml", std::process::id()));
+        fs::write(
+            &path,
+            "det_A:\n  type: float\n  tolerance: 1.0e-4\nA_inv:\n  type: matrix\n\"*\":\n  type: string\n",
+        )
+        .unwrap();
+        let schema = EmissionSchema::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let det = schema.entry("det_A").unwrap();
+        assert_eq!(
+            (det.value_type, det.tolerance),
+            (ValueType::Float, Some(1e-4))
+        );
+        let inverse = schema.entry("A_inv").unwrap();
+        assert_eq!(
+            (inverse.value_type, inverse.tolerance),
+            (ValueType::Matrix, None)
+        );
+        assert_eq!(schema.entry("other").unwrap().value_type, ValueType::String);
+
+        let without_wildcard: EmissionSchema =
+            serde_yaml::from_str("x:\n  type: integer\n").unwrap();
+        assert!(without_wildcard.entry("y").is_none());
+        assert!(EmissionSchema::default().is_empty());
+    }
+
+    #[test]
+    fn test_load_reports_errors() {
+        assert!(EmissionSchema::load("does/not/exist.yml").is_err());
+
+        let path =
+            std::env::temp_dir().join(format!("rufus-bad-schema-{}.yml", std::process::id()));
+        fs::write(&path, "det_A:\n  type: complex\n").unwrap();
+        let result = EmissionSchema::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.unwrap_err().contains("complex"));
+    }
+
+    #[test]
+    fn test_load_rejects_bad_tolerances() {
+        for tolerance in ["0", "-1.0e-4", ".nan", ".inf"] {
+            let yaml = format!("det_A:\n  type: float\n  tolerance: {}\n", tolerance);
+            let result = serde_yaml::from_str::<EmissionSchema>(&yaml);
+            assert!(
+                result.unwrap_err().to_string().contains("positive number"),
+                "{}",
+                tolerance
+            );
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("rufus-zero-schema-{}.yml", std::process::id()));
+        fs::write(&path, "det_A:\n  type: float\n  tolerance: 0\n").unwrap();
+        let result = EmissionSchema::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result
+            .unwrap_err()
+            .contains("tolerance must be a positive number"));
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use super::{
+    Anonymizer, Emission, EmissionSchema, EmissionsGroup, NormalizationConfig, Roster, Symbol,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Derives a student's seed for an assignment as `HMAC-SHA256(secret, assignment || 0 || sid)`.
+///
+/// Autograders derive the same seed (from the same secret) to generate each student's
+/// randomized problem instance, so a value that only makes sense for one student's seed
+/// shows up when it's submitted by someone else.
+pub fn derive_seed(secret: &[u8], sid: &str, assignment: &str) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(assignment.as_bytes());
+    mac.update(&[0]);
+    mac.update(sid.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// A derived seed, in the forms autograders are likely to want.
+#[derive(Debug, Clone)]
+pub struct StudentSeed {
+    pub sid: String,
+    pub name: Option<String>,
+    pub seed: [u8; 32],
+}
+
+impl StudentSeed {
+    /// The first 8 bytes of the seed as an integer, for seeding ordinary RNGs.
+    pub fn as_u64(&self) -> u64 {
+        u64::from_be_bytes(self.seed[..8].try_into().unwrap())
+    }
+
+    pub fn as_hex(&self) -> String {
+        to_hex(&self.seed)
+    }
+}
+
+/// Derives the seed for every student in the roster.
+pub fn derive_seeds(secret: &[u8], roster: &Roster, assignment: &str) -> Vec<StudentSeed> {
+    roster
+        .entries()
+        .iter()
+        .map(|e| StudentSeed {
+            sid: e.sid.clone(),
+            name: e.name.clone(),
+            seed: derive_seed(secret, &e.sid, assignment),
+        })
+        .collect()
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[derive(Deserialize)]
+struct ExpectedRow {
+    sid: String,
+    emission_id: String,
+    value: String,
+}
+
+/// The output each student's seed should produce, loaded from a CSV with
+/// `sid,emission_id,value` columns written by the autograder's solution.
+#[derive(Debug, Default)]
+pub struct ExpectedOutputs {
+    // emission ID -> SID -> expected emission
+    by_id: HashMap<Symbol, HashMap<String, Emission>>,
+}
+
+/// An emission whose value is the expected output for a different student's seed.
+pub struct ForeignSeedMatch<'a> {
+    pub group: &'a EmissionsGroup<'a>,
+    pub emission_id: Symbol,
+    /// The SIDs whose expected output the emission matches
+    pub matches_sids: BTreeSet<String>,
+}
+
+impl ExpectedOutputs {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<ExpectedOutputs, String> {
+        let mut reader = csv::Reader::from_path(path).map_err(|e| e.to_string())?;
+        let mut expected = ExpectedOutputs::default();
+        for row in reader.deserialize::<ExpectedRow>() {
+            let row = row.map_err(|e| e.to_string())?;
+            expected.insert(&row.sid, Emission::new(&row.emission_id, &row.value));
+        }
+        Ok(expected)
+    }
+
+    pub fn insert(&mut self, sid: &str, emission: Emission) {
+        self.by_id
+            .entry(emission.id())
+            .or_default()
+            .insert(sid.to_string(), emission);
+    }
+
+    /// Replaces the SIDs with their pseudonyms, so they line up with anonymized submissions.
+    pub fn anonymize(&mut self, anonymizer: &Anonymizer) {
+        for by_sid in self.by_id.values_mut() {
+            *by_sid = by_sid
+                .drain()
+                .map(|(sid, emission)| (anonymizer.pseudonym_for_key(&sid), emission))
+                .collect();
+        }
+    }
+
+    /// Types and normalizes the expected values the same way as submitted emissions, so
+    /// they compare consistently.
+    pub fn normalize(&mut self, schema: &EmissionSchema, config: &NormalizationConfig) {
+        for (id, by_sid) in self.by_id.iter_mut() {
+            for emission in by_sid.values_mut() {
+                if let Some(entry) = schema.entry(id.as_str()) {
+                    if let Err(e) = emission.apply_schema(entry) {
+                        eprintln!(
+                            "Error parsing emission \"{}\" as {}: {}",
+                            id, entry.value_type, e
+                        );
+                    }
+                }
+                emission.normalize(config.for_id(id.as_str()));
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    /// Finds emissions that don't match the expected output for any of the submission's own
+    /// submitters, but do match the expected output for another student.
+    pub fn find_foreign_matches<'a>(
+        &self,
+        groups: &'a [EmissionsGroup<'a>],
+    ) -> Vec<ForeignSeedMatch<'a>> {
+        let mut matches = vec![];
+        for group in groups {
+            let own_sids: BTreeSet<&String> = group
+                .submission()
+                .submitters()
+                .iter()
+                .filter_map(|s| s.sid.as_ref())
+                .collect();
+            if own_sids.is_empty() {
+                continue;
+            }
+
+            for emission in group.emissions() {
+                let Some(by_sid) = self.by_id.get(&emission.id()) else {
+                    continue;
+                };
+                let matches_own = own_sids
+                    .iter()
+                    .any(|sid| by_sid.get(*sid).is_some_and(|e| e.matches(emission)));
+                if matches_own {
+                    continue;
+                }
+
+                let matches_sids: BTreeSet<String> = by_sid
+                    .iter()
+                    .filter(|(sid, e)| !own_sids.contains(sid) && e.matches(emission))
+                    .map(|(sid, _)| sid.clone())
+                    .collect();
+                if !matches_sids.is_empty() {
+                    matches.push(ForeignSeedMatch {
+                        group,
+                        emission_id: emission.id(),
+                        matches_sids,
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gradescope::types::Submitter, sources::SimpleSubmission};
+    use itertools::Itertools;
+
+    fn submission(sids: &[&str], values: &[(&str, &str)]) -> SimpleSubmission {
+        let submitter = |sid: &str| Submitter {
+            name: format!("Student {}", sid),
+            sid: Some(sid.to_string()),
+            email: String::new(),
+        };
+        let mut submission = SimpleSubmission::new(submitter(sids[0]));
+        submission
+            .submitters
+            .extend(sids[1..].iter().map(|s| submitter(s)));
+        submission.emissions = values.iter().map(|(id, v)| Emission::new(id, v)).collect();
+        submission
+    }
+
+    #[test]
+    fn test_seeds_are_deterministic_and_distinct() {
+        let a = derive_seed(b"secret", "1234", "hw1");
+        assert_eq!(a, derive_seed(b"secret", "1234", "hw1"));
+        assert_ne!(a, derive_seed(b"secret", "1235", "hw1"));
+        assert_ne!(a, derive_seed(b"secret", "1234", "hw2"));
+        assert_ne!(a, derive_seed(b"other", "1234", "hw1"));
+        // The separator keeps (assignment, sid) pairs from running together
+        assert_ne!(
+            derive_seed(b"secret", "1234", "hw1"),
+            derive_seed(b"secret", "234", "hw11")
+        );
+    }
+
+    #[test]
+    fn test_find_foreign_matches() {
+        let mut expected = ExpectedOutputs::default();
+        for (sid, x, y) in [("1", "10", "a"), ("2", "20", "b"), ("3", "30", "a")] {
+            expected.insert(sid, Emission::new("x", x));
+            expected.insert(sid, Emission::new("y", y));
+        }
+
+        let subs = [
+            // Correct for their own seed, even though y is also 3's answer
+            submission(&["1"], &[("x", "10"), ("y", "a")]),
+            // 2 turned in 1's x, and a y nobody was expecting
+            submission(&["2"], &[("x", "10"), ("y", "c")]),
+            // 3 submitted with 2, so 2's answer isn't foreign to them
+            submission(&["3", "2"], &[("x", "20"), ("z", "10")]),
+            // 4 has no expected output, so every match is foreign
+            submission(&["4"], &[("x", "30"), ("y", "a")]),
+        ];
+        let groups: Vec<EmissionsGroup> = subs
+            .iter()
+            .map(|s| EmissionsGroup::from_submission(s))
+            .collect();
+
+        let matches: Vec<_> = expected
+            .find_foreign_matches(&groups)
+            .iter()
+            .map(|m| {
+                (
+                    m.group.submission().submitters()[0].sid.clone().unwrap(),
+                    m.emission_id.to_string(),
+                    m.matches_sids.iter().cloned().collect::<Vec<_>>(),
+                )
+            })
+            .sorted()
+            .collect();
+        let expected_matches = |sid: &str, id: &str, sids: &[&str]| {
+            (
+                sid.to_string(),
+                id.to_string(),
+                sids.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            matches,
+            vec![
+                expected_matches("2", "x", &["1"]),
+                expected_matches("4", "x", &["3"]),
+                expected_matches("4", "y", &["1", "3"]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use super::{EmissionsGroup, Symbol};
+
+/// How often each value of each emission ID occurs across the class, for estimating how
+/// likely a match is by chance.
+#[derive(Debug, Clone, Default)]
+pub struct ValueFrequencies {
+    // emission ID -> (submissions that emitted it, normalized value -> submissions with it)
+    by_id: HashMap<Symbol, (usize, HashMap<Symbol, usize>)>,
+}
+
+impl ValueFrequencies {
+    pub fn new(groups: &[EmissionsGroup]) -> Self {
+        let mut by_id: HashMap<Symbol, (usize, HashMap<Symbol, usize>)> = HashMap::new();
+        for emission in groups.iter().flat_map(|g| g.emissions()) {
+            let (emitted, values) = by_id.entry(emission.id()).or_default();
+            *emitted += 1;
+            *values.entry(emission.key()).or_default() += 1;
+        }
+        ValueFrequencies { by_id }
+    }
+
+    /// The fraction of the submissions that emitted `id` whose (normalized) value is `value`.
+    pub fn frequency(&self, id: Symbol, value: Symbol) -> f64 {
+        match self.by_id.get(&id) {
+            Some((emitted, values)) if *emitted > 0 => {
+                *values.get(&value).unwrap_or(&0) as f64 / *emitted as f64
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Estimates the probability that `n` students share the given `(id, value)` pairs by
+    /// coincidence: given the first student's values, the chance that the other `n - 1`
+    /// independently give the same ones, with each value's chance taken from the class and IDs
+    /// assumed independent.
+    ///
+    /// The group's own members count toward the class frequencies, so the estimate is
+    /// conservative: a value only the group submitted still gets a nonzero chance.
+    pub fn coincidence_probability(&self, values: &[(Symbol, Symbol)], n: usize) -> f64 {
+        let others = n.saturating_sub(1) as i32;
+        values
+            .iter()
+            .map(|(id, value)| self.frequency(*id, *value).powi(others))
+            .product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gradescope::types::Submitter, rufus::Emission, sources::SimpleSubmission};
+
+    #[test]
+    fn test_rare_values_are_unlikely() {
+        // Everyone gets x right; only the first two share a y
+        let subs: Vec<SimpleSubmission> = (0..10)
+            .map(|i| {
+                let mut submission = SimpleSubmission::new(Submitter {
+                    name: i.to_string(),
+                    sid: None,
+                    email: String::new(),
+                });
+                let y = if i < 2 {
+                    "shared".to_string()
+                } else {
+                    i.to_string()
+                };
+                submission.emissions = vec![Emission::new("x", "1"), Emission::new("y", &y)];
+                submission
+            })
+            .collect();
+        let groups: Vec<EmissionsGroup> = subs
+            .iter()
+            .map(|s| EmissionsGroup::from_submission(s))
+            .collect();
+        let frequencies = ValueFrequencies::new(&groups);
+
+        let (x, y) = (Symbol::intern("x"), Symbol::intern("y"));
+        let common = (x, Symbol::intern("1"));
+        let rare = (y, Symbol::intern("shared"));
+        assert_eq!(frequencies.frequency(x, Symbol::intern("1")), 1.0);
+        assert_eq!(frequencies.frequency(y, Symbol::intern("missing")), 0.0);
+
+        assert_eq!(frequencies.coincidence_probability(&[common], 2), 1.0);
+        assert!((frequencies.coincidence_probability(&[common, rare], 2) - 0.2).abs() < 1e-12);
+        // Each extra member has to match by chance too
+        assert!((frequencies.coincidence_probability(&[rare], 3) - 0.04).abs() < 1e-12);
+    }
+}
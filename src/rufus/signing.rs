@@ -0,0 +1,267 @@
+use std::collections::{BTreeSet, HashMap};
+
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use sha2::Sha256;
+
+use super::{to_hex, Emission, EmissionsGroup, Symbol};
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn emission_mac(secret: &[u8], id: &str, value: &str, nonce: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    for (i, part) in [id, value, nonce].iter().enumerate() {
+        if i > 0 {
+            mac.update(&[0]);
+        }
+        mac.update(part.as_bytes());
+    }
+    mac
+}
+
+/// The tag for an emission: hex `HMAC-SHA256(secret, id || 0 || value || 0 || nonce)`.
+pub fn sign_emission(secret: &[u8], id: &str, value: &str, nonce: &str) -> String {
+    to_hex(
+        &emission_mac(secret, id, value, nonce)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+/// Formats a signed emission line (`*id*base64(value)*nonce*tag`) as an autograder would
+/// print it.
+pub fn format_signed_emission(secret: &[u8], id: &str, value: &str, nonce: &str) -> String {
+    format!(
+        "*{}*{}*{}*{}",
+        id,
+        b64.encode(value),
+        nonce,
+        sign_emission(secret, id, value, nonce)
+    )
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Whether an emission's signature checks out against the course secret.
+pub fn verify_emission(secret: &[u8], emission: &Emission) -> bool {
+    match &emission.signature {
+        Some(sig) => from_hex(&sig.tag).is_some_and(|tag| {
+            emission_mac(secret, emission.id().as_str(), emission.value(), &sig.nonce)
+                .verify_slice(&tag)
+                .is_ok()
+        }),
+        None => false,
+    }
+}
+
+/// Why an emission can't be trusted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthenticityIssue {
+    /// The emission has no signature at all, so it wasn't printed by the autograder
+    Forged,
+    /// The signature doesn't match the id/value/nonce, so something was edited
+    Tampered,
+    /// The signature is valid, but the same nonce was also submitted by other students
+    Replayed { other_groups: Vec<usize> },
+}
+
+impl std::fmt::Display for AuthenticityIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthenticityIssue::Forged => write!(f, "Forged (unsigned)"),
+            AuthenticityIssue::Tampered => write!(f, "Tampered (invalid signature)"),
+            AuthenticityIssue::Replayed { .. } => write!(f, "Replayed (nonce reused)"),
+        }
+    }
+}
+
+pub struct AuthenticityFinding<'a> {
+    pub group: &'a EmissionsGroup<'a>,
+    pub emission_id: Symbol,
+    pub issue: AuthenticityIssue,
+}
+
+/// Verifies every emission in `groups`, reporting unsigned, tampered, and replayed
+/// emissions. Replays are valid emissions whose nonce also appears in a submission by a
+/// different set of submitters; `other_groups` indexes into `groups`.
+pub fn verify_emissions<'a>(
+    groups: &'a [EmissionsGroup<'a>],
+    secret: &[u8],
+) -> Vec<AuthenticityFinding<'a>> {
+    let submitter_key = |g: &EmissionsGroup| -> BTreeSet<String> {
+        g.submission()
+            .submitters()
+            .iter()
+            .map(|s| s.key().to_string())
+            .collect()
+    };
+
+    let mut findings = vec![];
+    let mut verified: Vec<(usize, &'a Emission, &'a String)> = vec![];
+    let mut nonce_users: HashMap<&String, BTreeSet<usize>> = HashMap::new();
+    for (i, group) in groups.iter().enumerate() {
+        for emission in group.emissions().sorted_by_key(|e| e.id().as_str()) {
+            let issue = match &emission.signature {
+                None => AuthenticityIssue::Forged,
+                Some(_) if !verify_emission(secret, emission) => AuthenticityIssue::Tampered,
+                Some(sig) => {
+                    nonce_users.entry(&sig.nonce).or_default().insert(i);
+                    verified.push((i, emission, &sig.nonce));
+                    continue;
+                }
+            };
+            findings.push(AuthenticityFinding {
+                group,
+                emission_id: emission.id(),
+                issue,
+            });
+        }
+    }
+
+    // A nonce shared by different submitters means a valid emission was copied
+    for (i, emission, nonce) in verified {
+        let me = submitter_key(&groups[i]);
+        let other_groups: Vec<usize> = nonce_users[nonce]
+            .iter()
+            .filter(|j| **j != i && submitter_key(&groups[**j]) != me)
+            .cloned()
+            .collect();
+        if !other_groups.is_empty() {
+            findings.push(AuthenticityFinding {
+                group: &groups[i],
+                emission_id: emission.id(),
+                issue: AuthenticityIssue::Replayed { other_groups },
+            });
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gradescope::types::Submitter, sources::SimpleSubmission};
+
+    fn submission(sid: &str, lines: &[String]) -> SimpleSubmission {
+        let mut submission = SimpleSubmission::new(Submitter {
+            name: format!("Student {}", sid),
+            sid: Some(sid.to_string()),
+            email: String::new(),
+        });
+        submission.emissions = lines.iter().map(|l| Emission::parse(l).unwrap()).collect();
+        submission
+    }
+
+    fn issues(findings: &[AuthenticityFinding]) -> Vec<(String, String, AuthenticityIssue)> {
+        findings
+            .iter()
+            .map(|f| {
+                let sid = f.group.submission().submitters()[0].sid.clone().unwrap();
+                (sid, f.emission_id.to_string(), f.issue.clone())
+            })
+            .sorted_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)))
+            .collect()
+    }
+
+    #[test]
+    fn test_signed_emission_round_trip() {
+        let line = format_signed_emission(b"secret", "det_A", "-2", "abc123");
+        let emission = Emission::parse(&line).unwrap();
+        assert_eq!(emission.value(), "-2");
+        assert_eq!(emission.signature.as_ref().unwrap().nonce, "abc123");
+        assert!(verify_emission(b"secret", &emission));
+        assert!(!verify_emission(b"wrong secret", &emission));
+
+        // Changing the value (or nonce) invalidates the tag
+        let tag = &emission.signature.as_ref().unwrap().tag;
+        let tampered = Emission::parse(&format!("*det_A*{}*abc123*{}", b64.encode("-3"), tag));
+        assert!(!verify_emission(b"secret", &tampered.unwrap()));
+        let tampered = Emission::parse(&format!("*det_A*{}*abc124*{}", b64.encode("-2"), tag));
+        assert!(!verify_emission(b"secret", &tampered.unwrap()));
+
+        // Unsigned emissions still parse, but never verify
+        let unsigned = Emission::parse(&format!("*det_A*{}", b64.encode("-2"))).unwrap();
+        assert!(unsigned.signature.is_none());
+        assert!(!verify_emission(b"secret", &unsigned));
+    }
+
+    #[test]
+    fn test_verify_emissions_finds_forged_tampered_and_replayed() {
+        let signed = |id: &str, value: &str, nonce: &str| {
+            format_signed_emission(b"secret", id, value, nonce)
+        };
+        let copied = signed("x", "1", "n1");
+        let tag = sign_emission(b"secret", "y", "2", "n3");
+        let subs = [
+            submission("1", &[copied.clone(), signed("y", "2", "n2")]),
+            // Copies 1's signed x, edits a signed y, and makes up an unsigned z
+            submission(
+                "2",
+                &[
+                    copied,
+                    format!("*y*{}*n3*{}", b64.encode("5"), tag),
+                    format!("*z*{}", b64.encode("3")),
+                ],
+            ),
+            submission("3", &[signed("x", "1", "n4"), signed("y", "2", "n5")]),
+        ];
+        let groups: Vec<EmissionsGroup> = subs
+            .iter()
+            .map(|s| EmissionsGroup::from_submission(s))
+            .collect();
+
+        let replayed = |other: usize| AuthenticityIssue::Replayed {
+            other_groups: vec![other],
+        };
+        assert_eq!(
+            issues(&verify_emissions(&groups, b"secret")),
+            vec![
+                ("1".to_string(), "x".to_string(), replayed(1)),
+                ("2".to_string(), "x".to_string(), replayed(0)),
+                (
+                    "2".to_string(),
+                    "y".to_string(),
+                    AuthenticityIssue::Tampered
+                ),
+                ("2".to_string(), "z".to_string(), AuthenticityIssue::Forged),
+            ]
+        );
+
+        // Under the wrong secret every signed emission is tampered, so none are replays
+        let findings = verify_emissions(&groups, b"other");
+        assert_eq!(findings.len(), 7);
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|f| f.issue == AuthenticityIssue::Tampered)
+                .count(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_resubmitting_own_emission_is_not_replayed() {
+        // Two submissions by the same student (e.g. from history) may share a nonce
+        let line = format_signed_emission(b"secret", "x", "1", "n1");
+        let subs = [
+            submission("1", std::slice::from_ref(&line)),
+            submission("1", &[line]),
+        ];
+        let groups: Vec<EmissionsGroup> = subs
+            .iter()
+            .map(|s| EmissionsGroup::from_submission(s))
+            .collect();
+        assert!(verify_emissions(&groups, b"secret").is_empty());
+    }
+}
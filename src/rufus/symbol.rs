@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+/// An interned string. Emission IDs and values are interned in a global symbol table, so
+/// equal strings share one allocation and comparing them is an integer comparison.
+///
+/// Symbols order by when they were interned, which is cheap but arbitrary. Output that
+/// should be alphabetical has to sort by `as_str` itself.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    /// Returns the symbol for `s`, adding it to the symbol table if it's new. Interned
+    /// strings live for the rest of the program.
+    pub fn intern(s: &str) -> Symbol {
+        if let Some(symbol) = interner().read().unwrap().ids.get(s) {
+            return *symbol;
+        }
+
+        let mut interner = interner().write().unwrap();
+        // Someone else may have interned it between the locks
+        if let Some(symbol) = interner.ids.get(s) {
+            return *symbol;
+        }
+        let symbol = Symbol(interner.strings.len() as u32);
+        let leaked: &'static str = Box::leak(s.to_string().into_boxed_str());
+        interner.strings.push(leaked);
+        interner.ids.insert(leaked, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        interner().read().unwrap().strings[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Symbol::intern(s)
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interning() {
+        let a = Symbol::intern("symbol-test-b");
+        let b = Symbol::intern("symbol-test-a");
+        assert_eq!(a, Symbol::intern("symbol-test-b"));
+        assert_ne!(a, b);
+        assert_eq!(a.as_str(), "symbol-test-b");
+        // Ordered by interning order, not by string
+        assert!(a < b);
+    }
+}
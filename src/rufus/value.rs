@@ -0,0 +1,218 @@
+use serde::Deserialize;
+
+use super::format_rounded;
+
+/// The type an emission's decoded value should be parsed as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    Integer,
+    Float,
+    Vector,
+    Matrix,
+    String,
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ValueType::Integer => "integer",
+            ValueType::Float => "float",
+            ValueType::Vector => "vector",
+            ValueType::Matrix => "matrix",
+            ValueType::String => "string",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// An emission value parsed according to its `ValueType`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmissionValue {
+    Integer(i64),
+    Float(f64),
+    Vector(Vec<f64>),
+    Matrix(Vec<Vec<f64>>),
+    String(String),
+}
+
+impl EmissionValue {
+    pub fn parse(value: &str, value_type: ValueType) -> Result<EmissionValue, String> {
+        match value_type {
+            ValueType::Integer => value
+                .trim()
+                .parse::<i64>()
+                .map(EmissionValue::Integer)
+                .map_err(|e| e.to_string()),
+            ValueType::Float => value
+                .trim()
+                .parse::<f64>()
+                .map(EmissionValue::Float)
+                .map_err(|e| e.to_string()),
+            ValueType::Vector => {
+                let rows = parse_matrix(value).ok_or("not a vector of numbers")?;
+                // Accept both row and column vectors
+                if rows.len() == 1 || rows.iter().all(|r| r.len() == 1) {
+                    Ok(EmissionValue::Vector(rows.into_iter().flatten().collect()))
+                } else {
+                    Err(format!(
+                        "expected a vector, got a {} row matrix",
+                        rows.len()
+                    ))
+                }
+            }
+            ValueType::Matrix => {
+                let rows = parse_matrix(value).ok_or("not a matrix of numbers")?;
+                if rows.iter().all(|r| r.len() == rows[0].len()) {
+                    Ok(EmissionValue::Matrix(rows))
+                } else {
+                    Err("matrix rows have different lengths".to_string())
+                }
+            }
+            ValueType::String => Ok(EmissionValue::String(value.to_string())),
+        }
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            EmissionValue::Integer(_) => ValueType::Integer,
+            EmissionValue::Float(_) => ValueType::Float,
+            EmissionValue::Vector(_) => ValueType::Vector,
+            EmissionValue::Matrix(_) => ValueType::Matrix,
+            EmissionValue::String(_) => ValueType::String,
+        }
+    }
+
+    /// A canonical string for comparing values, with every number rounded to `tolerance`
+    /// when one is given.
+    pub fn canonical(&self, tolerance: Option<f64>) -> String {
+        let number = |x: &f64| match tolerance {
+            Some(tol) => format_rounded(*x, tol),
+            None => x.to_string(),
+        };
+        let row = |row: &Vec<f64>| {
+            format!(
+                "[{}]",
+                row.iter().map(number).collect::<Vec<_>>().join(", ")
+            )
+        };
+
+        match self {
+            EmissionValue::Integer(i) => i.to_string(),
+            EmissionValue::Float(x) => number(x),
+            EmissionValue::Vector(v) => row(v),
+            EmissionValue::Matrix(m) => {
+                format!("[{}]", m.iter().map(row).collect::<Vec<_>>().join(", "))
+            }
+            EmissionValue::String(s) => s.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for EmissionValue {
+    /// Pretty-prints the value, laying matrices out with aligned columns.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmissionValue::Integer(i) => write!(f, "{}", i),
+            EmissionValue::Float(x) => write!(f, "{}", x),
+            EmissionValue::Vector(v) => write!(
+                f,
+                "[{}]",
+                v.iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            EmissionValue::Matrix(m) => {
+                let cells: Vec<Vec<String>> = m
+                    .iter()
+                    .map(|r| r.iter().map(|x| x.to_string()).collect())
+                    .collect();
+                let width = cells.iter().flatten().map(|c| c.len()).max().unwrap_or(0);
+                for (i, row) in cells.iter().enumerate() {
+                    let (open, close) = match (i, cells.len()) {
+                        (_, 1) => ("[", "]"),
+                        (0, _) => ("⎡", "⎤"),
+                        (i, n) if i == n - 1 => ("⎣", "⎦"),
+                        _ => ("⎢", "⎥"),
+                    };
+                    let row = row
+                        .iter()
+                        .map(|c| format!("{:>width$}", c, width = width))
+                        .collect::<Vec<_>>()
+                        .join("  ");
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{} {} {}", open, row, close)?;
+                }
+                Ok(())
+            }
+            EmissionValue::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Parses a vector or matrix written as text. Rows may be separated by newlines, `;`, or
+/// nested brackets (`[[1, 2], [3, 4]]`), and entries by commas or whitespace.
+pub fn parse_matrix(value: &str) -> Option<Vec<Vec<f64>>> {
+    let row_break = regex::Regex::new(r"[\]\)]\s*,?\s*[\[\(]").unwrap();
+    let flattened = row_break.replace_all(value.trim(), ";");
+    let rows = flattened
+        .replace(['[', ']', '(', ')'], "")
+        .split([';', '\n'])
+        .map(|row| {
+            row.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|e| !e.is_empty())
+                .map(|e| e.parse::<f64>().ok())
+                .collect::<Option<Vec<f64>>>()
+        })
+        .collect::<Option<Vec<Vec<f64>>>>()?
+        .into_iter()
+        .filter(|row| !row.is_empty())
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        None
+    } else {
+        Some(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!(
+            EmissionValue::parse(" 42\n", ValueType::Integer).unwrap(),
+            EmissionValue::Integer(42)
+        );
+        assert_eq!(
+            EmissionValue::parse("1\n2\n3", ValueType::Vector).unwrap(),
+            EmissionValue::Vector(vec![1.0, 2.0, 3.0])
+        );
+        assert_eq!(
+            EmissionValue::parse("[[1, 2], [3, 4]]", ValueType::Matrix).unwrap(),
+            EmissionValue::Matrix(vec![vec![1.0, 2.0], vec![3.0, 4.0]])
+        );
+        assert!(EmissionValue::parse("1 2\n3", ValueType::Matrix).is_err());
+        assert!(EmissionValue::parse("[[1, 2], [3, 4]]", ValueType::Vector).is_err());
+        assert!(EmissionValue::parse("abc", ValueType::Float).is_err());
+    }
+
+    #[test]
+    fn test_canonical_uses_tolerance() {
+        let a = EmissionValue::parse("1 2\n3 4.00001", ValueType::Matrix).unwrap();
+        let b = EmissionValue::parse("[[1.0, 2.0], [3.0, 4.0]]", ValueType::Matrix).unwrap();
+        assert_ne!(a.canonical(None), b.canonical(None));
+        assert_eq!(a.canonical(Some(1e-3)), b.canonical(Some(1e-3)));
+    }
+
+    #[test]
+    fn test_display_aligns_matrix() {
+        let m = EmissionValue::Matrix(vec![vec![1.0, -10.0], vec![100.0, 2.5]]);
+        assert_eq!(m.to_string(), "⎡   1  -10 ⎤\n⎣ 100  2.5 ⎦");
+    }
+}
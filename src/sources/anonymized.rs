@@ -0,0 +1,50 @@
+use crate::{
+    gradescope::types::{Results, Score, SubmissionTrait, Submitter},
+    rufus::{Anonymizer, Emission, EmissionParseError},
+};
+
+use super::BoxedSubmission;
+
+/// A submission from any source with its submitters replaced by pseudonyms. Everything else
+/// is passed through untouched.
+pub struct AnonymizedSubmission {
+    inner: BoxedSubmission,
+    submitters: Vec<Submitter>,
+}
+
+impl AnonymizedSubmission {
+    pub fn new(inner: BoxedSubmission, anonymizer: &Anonymizer) -> Self {
+        let submitters = inner
+            .submitters()
+            .iter()
+            .map(|s| anonymizer.anonymize(s))
+            .collect();
+        AnonymizedSubmission { inner, submitters }
+    }
+}
+
+impl SubmissionTrait for AnonymizedSubmission {
+    fn submitters(&self) -> &Vec<Submitter> {
+        &self.submitters
+    }
+
+    fn created_at(&self) -> &String {
+        self.inner.created_at()
+    }
+
+    fn score(&self) -> &Score {
+        self.inner.score()
+    }
+
+    fn status(&self) -> &String {
+        self.inner.status()
+    }
+
+    fn results(&self) -> &Option<Results> {
+        self.inner.results()
+    }
+
+    fn emissions(&self) -> Vec<Result<Emission, EmissionParseError>> {
+        self.inner.emissions()
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Deserialize;
+
+use crate::{gradescope::types::Submitter, rufus::Emission};
+
+use super::{BoxedSubmission, SimpleSubmission, SubmissionSource};
+
+/// A CSV of already-decoded emissions, one per row:
+///
+/// ```csv
+/// student,emission_id,value
+/// Alice,det_A,42
+/// ```
+///
+/// Optional `sid` and `email` columns fill in the rest of the submitter. Rows are grouped
+/// into one submission per student, so this is a convenient target for exporting results
+/// from any other autograder.
+pub struct CsvSource {
+    path: Utf8PathBuf,
+}
+
+#[derive(Deserialize)]
+struct CsvRow {
+    student: String,
+    emission_id: String,
+    value: String,
+    #[serde(default)]
+    sid: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+}
+
+impl CsvSource {
+    pub fn new(path: &Utf8Path) -> Self {
+        CsvSource {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl SubmissionSource for CsvSource {
+    fn describe(&self) -> String {
+        self.path.to_string()
+    }
+
+    fn load(&self) -> Result<Vec<BoxedSubmission>, String> {
+        let mut reader = ::csv::Reader::from_path(&self.path).map_err(|e| e.to_string())?;
+
+        // Keep students in the order they first appear
+        let mut order: Vec<String> = vec![];
+        let mut submissions: HashMap<String, SimpleSubmission> = HashMap::new();
+        for row in reader.deserialize::<CsvRow>() {
+            let row = row.map_err(|e| e.to_string())?;
+            let submission = submissions.entry(row.student.clone()).or_insert_with(|| {
+                order.push(row.student.clone());
+                SimpleSubmission::new(Submitter {
+                    name: row.student.clone(),
+                    sid: row.sid.clone(),
+                    email: row.email.clone().unwrap_or_default(),
+                })
+            });
+            submission
+                .emissions
+                .push(Emission::new(&row.emission_id, &row.value));
+        }
+
+        Ok(order
+            .iter()
+            .filter_map(|student| submissions.remove(student))
+            .map(|s| Box::new(s) as BoxedSubmission)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rufus::EmissionsGroup;
+
+    #[test]
+    fn test_load_groups_rows_by_student() {
+        let path = std::env::temp_dir().join(format!("rufus-csv-{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "student,emission_id,value,sid\nAlice,a,1,111\nBob,a,1,222\nAlice,b,\"[1, 2]\n[3, 4]\",111\n",
+        )
+        .unwrap();
+
+        let source = CsvSource::new(Utf8Path::from_path(&path).unwrap());
+        let submissions = source.load().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(submissions.len(), 2);
+        assert_eq!(submissions[0].submitters()[0].name, "Alice");
+        assert_eq!(submissions[0].submitters()[0].sid.as_deref(), Some("111"));
+
+        let alice = EmissionsGroup::from_submission(submissions[0].as_ref());
+        let bob = EmissionsGroup::from_submission(submissions[1].as_ref());
+        assert_eq!(alice.len(), 2);
+        assert_eq!(alice.emissions_map()[&"b".into()].value(), "[1, 2]\n[3, 4]");
+        assert!(!alice.matches(&bob));
+    }
+}
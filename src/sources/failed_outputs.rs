@@ -0,0 +1,140 @@
+use crate::{
+    gradescope::types::{is_emission_test, Results, Score, SubmissionTrait, Submitter, Test},
+    rufus::{Emission, EmissionParseError},
+};
+
+use super::BoxedSubmission;
+
+/// Prefixes the test number in a failed output's emission ID, so it can't collide with a real
+/// emission ID like `1.1`.
+const FAILED_OUTPUT_PREFIX: &str = "test:";
+
+/// A submission whose failed graded tests count as emissions too: each failed (or partially
+/// credited) test's output becomes an emission keyed by the test's number (e.g. `test:1.1`).
+/// This lets Rufus hunt on autograder output from assignments that never printed emissions.
+pub struct FailedOutputSubmission {
+    inner: BoxedSubmission,
+}
+
+impl FailedOutputSubmission {
+    pub fn new(inner: BoxedSubmission) -> Self {
+        FailedOutputSubmission { inner }
+    }
+
+    /// The pseudo-emissions taken from the failed tests' output.
+    fn failed_outputs(&self) -> Vec<Emission> {
+        match self.inner.results() {
+            Some(Results::Processed(results)) => results
+                .tests
+                .iter()
+                .filter(|t| !is_emission_test(t) && t.is_incorrect())
+                .filter_map(|t| {
+                    let output = t.output.as_deref()?.trim();
+                    (!output.is_empty()).then(|| {
+                        Emission::new(&format!("{}{}", FAILED_OUTPUT_PREFIX, t.number), output)
+                    })
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl SubmissionTrait for FailedOutputSubmission {
+    fn submitters(&self) -> &Vec<Submitter> {
+        self.inner.submitters()
+    }
+
+    fn created_at(&self) -> &String {
+        self.inner.created_at()
+    }
+
+    fn score(&self) -> &Score {
+        self.inner.score()
+    }
+
+    fn status(&self) -> &String {
+        self.inner.status()
+    }
+
+    fn results(&self) -> &Option<Results> {
+        self.inner.results()
+    }
+
+    /// A failed output's parent test is the failed test itself.
+    fn parent_test(&self, id: &str) -> Option<&Test> {
+        let Some(number) = id.strip_prefix(FAILED_OUTPUT_PREFIX) else {
+            return self.inner.parent_test(id);
+        };
+        match self.results() {
+            Some(Results::Processed(results)) => results
+                .tests
+                .iter()
+                .find(|t| !is_emission_test(t) && t.number == number),
+            _ => None,
+        }
+    }
+
+    fn emissions(&self) -> Vec<Result<Emission, EmissionParseError>> {
+        let mut emissions = self.inner.emissions();
+        emissions.extend(self.failed_outputs().into_iter().map(Ok));
+        emissions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gradescope::types::LatestSubmission,
+        rufus::{EmissionsGroup, Symbol},
+    };
+
+    #[test]
+    fn test_failed_outputs_become_emissions() {
+        let export: std::collections::HashMap<String, LatestSubmission> =
+            serde_yaml::from_str(include_str!("../../tests/fixtures/parents.yml")).unwrap();
+        let submission = export.into_values().find(|s| s.score < 10.0).unwrap();
+        let submission = FailedOutputSubmission::new(Box::new(submission));
+
+        let emissions: Vec<_> = submission
+            .emissions()
+            .into_iter()
+            .map(|e| e.unwrap())
+            .map(|e| (e.id().to_string(), e.value().to_string()))
+            .collect();
+        // The real emissions come first; the passing test "y" isn't included
+        assert_eq!(
+            emissions,
+            vec![
+                ("x".to_string(), "1".to_string()),
+                ("y".to_string(), "2".to_string()),
+                ("test:1.1".to_string(), "Wrong answer".to_string()),
+            ]
+        );
+        // The pseudo-emission's parent test is the failed test itself
+        assert!(submission.parent_test("test:1.1").unwrap().is_incorrect());
+    }
+
+    #[test]
+    fn test_failed_outputs_dont_collide_with_emission_ids() {
+        // Emit "1.1" as well as failing test 1.1
+        let yaml = include_str!("../../tests/fixtures/parents.yml").replace("*y*Mg==", "*1.1*Mg==");
+        let export: std::collections::HashMap<String, LatestSubmission> =
+            serde_yaml::from_str(&yaml).unwrap();
+        let submission = export.into_values().find(|s| s.score < 10.0).unwrap();
+        let submission = FailedOutputSubmission::new(Box::new(submission));
+
+        let group = EmissionsGroup::from_submission(&submission);
+        let value = |id: &str| {
+            group
+                .emissions_map()
+                .get(&Symbol::intern(id))
+                .map(|e| e.value())
+        };
+        assert_eq!(group.len(), 3);
+        assert_eq!(value("1.1"), Some("2"));
+        assert_eq!(value("test:1.1"), Some("Wrong answer"));
+        assert_eq!(submission.parent_test("test:1.1").unwrap().number, "1.1");
+    }
+}
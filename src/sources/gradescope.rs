@@ -0,0 +1,71 @@
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::gradescope::{
+    archive::{is_zip, ExportArchive},
+    loaders::load_export,
+    stream::stream_export,
+};
+
+use super::{BoxedSubmission, SubmissionSource};
+
+/// A Gradescope export: either its `submission_metadata.yml`, or the whole ZIP export.
+pub struct GradescopeSource {
+    path: Utf8PathBuf,
+    streaming: bool,
+}
+
+impl GradescopeSource {
+    pub fn new(path: &Utf8Path) -> Self {
+        GradescopeSource {
+            path: path.to_path_buf(),
+            streaming: false,
+        }
+    }
+
+    /// Parses the export one submission at a time, keeping only what's needed to extract
+    /// emissions. Only parsing is incremental: every submission is still loaded (without the
+    /// parts that were skipped) before grouping, so memory still grows with the class size.
+    /// Use this for exports too large to parse whole.
+    pub fn streaming(path: &Utf8Path) -> Self {
+        GradescopeSource {
+            path: path.to_path_buf(),
+            streaming: true,
+        }
+    }
+}
+
+impl SubmissionSource for GradescopeSource {
+    fn describe(&self) -> String {
+        self.path.to_string()
+    }
+
+    fn load(&self) -> Result<Vec<BoxedSubmission>, String> {
+        if is_zip(&self.path) {
+            let mut archive = ExportArchive::open(&self.path)?;
+            let submissions: Vec<BoxedSubmission> = if self.streaming {
+                archive
+                    .stream()?
+                    .map(|s| s.map(|(_, s)| Box::new(s) as BoxedSubmission))
+                    .collect::<Result<_, _>>()?
+            } else {
+                archive
+                    .load()?
+                    .into_values()
+                    .map(|s| Box::new(s) as BoxedSubmission)
+                    .collect()
+            };
+            return Ok(submissions);
+        }
+
+        if self.streaming {
+            return stream_export(&self.path)?
+                .map(|s| s.map(|(_, s)| Box::new(s) as BoxedSubmission))
+                .collect();
+        }
+
+        Ok(load_export(&self.path)?
+            .into_values()
+            .map(|s| Box::new(s) as BoxedSubmission)
+            .collect())
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{gradescope::types::Submitter, rufus::Emission};
+
+use super::{BoxedSubmission, SimpleSubmission, SubmissionSource};
+
+/// A directory of captured stdout logs, one file per student. The file stem is used as the
+/// student's name, and every line starting with `*` is parsed as an emission.
+pub struct LogDirSource {
+    path: Utf8PathBuf,
+}
+
+impl LogDirSource {
+    pub fn new(path: &Utf8Path) -> Self {
+        LogDirSource {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl SubmissionSource for LogDirSource {
+    fn describe(&self) -> String {
+        format!("{}/", self.path)
+    }
+
+    fn load(&self) -> Result<Vec<BoxedSubmission>, String> {
+        let mut entries = self
+            .path
+            .read_dir_utf8()
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+
+        let mut submissions: Vec<BoxedSubmission> = vec![];
+        for entry in entries.into_iter().filter(|e| e.path().is_file()) {
+            let log = fs::read_to_string(entry.path()).map_err(|e| e.to_string())?;
+            let name = entry.path().file_stem().unwrap_or(entry.file_name());
+
+            let mut submission = SimpleSubmission::new(Submitter {
+                name: name.to_string(),
+                sid: None,
+                email: String::new(),
+            });
+            submission.emissions = log
+                .lines()
+                .filter(|line| line.starts_with('*'))
+                .filter_map(|line| match Emission::parse(line) {
+                    Ok(emission) => Some(emission),
+                    Err(e) => {
+                        eprintln!("Error parsing emission in {}: {}", entry.path(), e);
+                        None
+                    }
+                })
+                .collect();
+            submissions.push(Box::new(submission));
+        }
+
+        Ok(submissions)
+    }
+}
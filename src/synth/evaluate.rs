@@ -0,0 +1,175 @@
+use std::collections::BTreeSet;
+
+use crate::{rufus::Grouping, synth::GroundTruth};
+
+/// An unordered pair of students, by submitter key.
+type Pair = (String, String);
+
+fn pair(a: &str, b: &str) -> Pair {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+fn pairs(members: &BTreeSet<String>) -> BTreeSet<Pair> {
+    members
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| members.iter().skip(i + 1).map(move |b| pair(a, b)))
+        .collect()
+}
+
+/// How much of a planted ring the reported groups found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingResult {
+    pub members: BTreeSet<String>,
+    /// Pairs of members that were reported in the same group
+    pub found_pairs: usize,
+    pub total_pairs: usize,
+    /// Whether some reported group contains every member of the ring
+    pub recovered: bool,
+}
+
+/// Scores reported groups against the planted rings of a synthetic export.
+///
+/// Groups are compared as pairs of students: a pair is a true positive if both students are in
+/// the same reported group and the same planted ring. Since `hunt` reports the same students
+/// once for every set of IDs they match on, the reported groups are deduplicated by members
+/// first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub rings: Vec<RingResult>,
+    /// Reported groups that don't contain a single planted pair
+    pub false_positive_groups: Vec<BTreeSet<String>>,
+}
+
+impl Evaluation {
+    pub fn new(truth: &GroundTruth, groupings: &[Grouping]) -> Self {
+        let reported: BTreeSet<BTreeSet<String>> = groupings
+            .iter()
+            .map(|g| {
+                g.groups()
+                    .iter()
+                    .flat_map(|group| group.submission().submitters())
+                    .map(|s| s.key().to_string())
+                    .collect::<BTreeSet<String>>()
+            })
+            .filter(|members| members.len() > 1)
+            .collect();
+        let rings: Vec<BTreeSet<String>> = truth
+            .rings
+            .iter()
+            .map(|ring| ring.iter().cloned().collect())
+            .collect();
+
+        let planted_pairs: BTreeSet<Pair> = rings.iter().flat_map(pairs).collect();
+        let reported_pairs: BTreeSet<Pair> = reported.iter().flat_map(pairs).collect();
+
+        let rings = rings
+            .into_iter()
+            .map(|members| {
+                let ring_pairs = pairs(&members);
+                RingResult {
+                    found_pairs: ring_pairs.intersection(&reported_pairs).count(),
+                    total_pairs: ring_pairs.len(),
+                    recovered: reported.iter().any(|r| members.is_subset(r)),
+                    members,
+                }
+            })
+            .collect();
+        let false_positive_groups = reported
+            .iter()
+            .filter(|members| pairs(members).is_disjoint(&planted_pairs))
+            .cloned()
+            .collect();
+
+        Evaluation {
+            true_positives: reported_pairs.intersection(&planted_pairs).count(),
+            false_positives: reported_pairs.difference(&planted_pairs).count(),
+            false_negatives: planted_pairs.difference(&reported_pairs).count(),
+            rings,
+            false_positive_groups,
+        }
+    }
+
+    /// The fraction of reported pairs that were planted (1 if nothing was reported).
+    pub fn precision(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    /// The fraction of planted pairs that were reported (1 if nothing was planted).
+    pub fn recall(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    pub fn f1(&self) -> f64 {
+        let (p, r) = (self.precision(), self.recall());
+        if p + r == 0.0 {
+            0.0
+        } else {
+            2.0 * p * r / (p + r)
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        1.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gradescope::types::SubmissionTrait,
+        rufus::{hunt, EmissionsGroup},
+        synth::{generate, SynthConfig},
+    };
+
+    #[test]
+    fn test_scores_pairs_against_planted_rings() {
+        let config = SynthConfig {
+            num_submissions: 60,
+            collision_rate: 0.0,
+            rings: vec![3, 2],
+            ..Default::default()
+        };
+        let (export, truth) = generate(&config);
+        let emissions: Vec<EmissionsGroup> = export.values().map(|s| s.parse_emissions()).collect();
+
+        // With no coincidental collisions, k=2 finds every ring and nothing else
+        let groupings: Vec<Grouping> = hunt(&emissions, 2, false)
+            .into_iter()
+            .filter(|g| g.len() > 1)
+            .collect();
+        let evaluation = Evaluation::new(&truth, &groupings);
+        assert_eq!(evaluation.true_positives, 4);
+        assert_eq!(evaluation.false_positives, 0);
+        assert!(evaluation.rings.iter().all(|r| r.recovered));
+        assert_eq!(evaluation.precision(), 1.0);
+        assert_eq!(evaluation.recall(), 1.0);
+
+        // Requiring more IDs than the rings share misses them all
+        let groupings: Vec<Grouping> = hunt(&emissions, config.ring_shared_ids + 1, false)
+            .into_iter()
+            .filter(|g| g.len() > 1)
+            .collect();
+        let evaluation = Evaluation::new(&truth, &groupings);
+        assert_eq!(evaluation.false_negatives, 4);
+        assert_eq!(evaluation.recall(), 0.0);
+    }
+}
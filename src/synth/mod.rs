@@ -0,0 +1,332 @@
+mod evaluate;
+
+pub use self::evaluate::*;
+
+use std::{collections::HashMap, fs, path::Path};
+
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::gradescope::types::{
+    Export, LatestSubmission, ProcessedResults, Results, Submitter, Test,
+};
+
+/// Settings for a synthetic Gradescope export.
+#[derive(Debug, Clone)]
+pub struct SynthConfig {
+    /// Number of submissions in the export
+    pub num_submissions: usize,
+    /// Number of emission IDs each submission emits
+    pub num_emission_ids: usize,
+    /// Chance that an honest emission is drawn from a small pool of common values (e.g. a
+    /// popular wrong answer), so unrelated students collide by coincidence
+    pub collision_rate: f64,
+    /// Sizes of the planted cheating rings
+    pub rings: Vec<usize>,
+    /// How many emission IDs the members of each ring copy from each other
+    pub ring_shared_ids: usize,
+    /// Seed for the generator, so exports are reproducible
+    pub seed: u64,
+}
+
+impl Default for SynthConfig {
+    fn default() -> Self {
+        SynthConfig {
+            num_submissions: 200,
+            num_emission_ids: 6,
+            collision_rate: 0.05,
+            rings: vec![3, 2, 2],
+            ring_shared_ids: 4,
+            seed: 0,
+        }
+    }
+}
+
+/// The planted cheating rings of a synthetic export, as lists of member SIDs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GroundTruth {
+    pub rings: Vec<Vec<String>>,
+}
+
+impl GroundTruth {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<GroundTruth, String> {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_yaml::from_str(&data).map_err(|e| e.to_string()))
+    }
+
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<(), String> {
+        let data = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, data).map_err(|e| e.to_string())
+    }
+}
+
+/// A small, seedable PRNG (SplitMix64). Synthetic data only needs to be reproducible, not
+/// cryptographically random.
+pub struct SynthRng(u64);
+
+impl SynthRng {
+    pub fn new(seed: u64) -> Self {
+        SynthRng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n.max(1)
+    }
+
+    /// A value in `[0, 1)`.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+}
+
+/// The number of common values honest collisions are drawn from.
+const COMMON_VALUES: u64 = 3;
+
+fn emission_id(i: usize) -> String {
+    format!("emission_{}", i)
+}
+
+/// A random 2x2 matrix, like a linear algebra autograder would emit.
+fn random_matrix(rng: &mut SynthRng) -> String {
+    let mut entry = || rng.below(2000) as i64 - 1000;
+    format!("[[{}, {}], [{}, {}]]", entry(), entry(), entry(), entry())
+}
+
+/// Generates a synthetic export with planted cheating rings, returning the export and the
+/// rings' members.
+pub fn generate(config: &SynthConfig) -> (Export, GroundTruth) {
+    let mut rng = SynthRng::new(config.seed);
+    let ids: Vec<String> = (0..config.num_emission_ids).map(emission_id).collect();
+    let common: HashMap<&String, Vec<String>> = ids
+        .iter()
+        .map(|id| {
+            (
+                id,
+                (0..COMMON_VALUES)
+                    .map(|_| random_matrix(&mut rng))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    // Everyone's honest values first
+    let mut values: Vec<Vec<String>> = (0..config.num_submissions)
+        .map(|_| {
+            ids.iter()
+                .map(|id| {
+                    if rng.unit() < config.collision_rate {
+                        common[id][rng.below(COMMON_VALUES) as usize].clone()
+                    } else {
+                        random_matrix(&mut rng)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let mut minutes_before_deadline: Vec<u64> = (0..config.num_submissions)
+        .map(|_| rng.below(7 * 24 * 60))
+        .collect();
+
+    // Then plant the rings on disjoint sets of students, copying the first member's values
+    let mut students: Vec<usize> = (0..config.num_submissions).collect();
+    rng.shuffle(&mut students);
+    let mut remaining = students.into_iter();
+    let mut truth = GroundTruth::default();
+    for ring_size in &config.rings {
+        let members: Vec<usize> = remaining.by_ref().take(*ring_size).collect();
+        if members.len() < 2 {
+            break;
+        }
+
+        let mut shared: Vec<usize> = (0..ids.len()).collect();
+        rng.shuffle(&mut shared);
+        shared.truncate(config.ring_shared_ids.min(ids.len()));
+
+        let leader = members[0];
+        for member in &members[1..] {
+            for id in &shared {
+                values[*member][*id] = values[leader][*id].clone();
+            }
+            // Copiers tend to submit shortly after the leader
+            minutes_before_deadline[*member] =
+                minutes_before_deadline[leader].saturating_sub(rng.below(30));
+        }
+        truth.rings.push(members.iter().map(|m| sid(*m)).collect());
+    }
+
+    let export = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, values)| {
+            let key = format!("submission_{}", 100000 + i);
+            let submission =
+                synthetic_submission(i, &ids, &values, minutes_before_deadline[i], &mut rng);
+            (key, submission)
+        })
+        .collect();
+
+    (export, truth)
+}
+
+fn sid(i: usize) -> String {
+    format!("{:08}", 10000000 + i)
+}
+
+fn synthetic_submission(
+    i: usize,
+    ids: &[String],
+    values: &[String],
+    minutes_before_deadline: u64,
+    rng: &mut SynthRng,
+) -> LatestSubmission {
+    let emissions = ids
+        .iter()
+        .zip(values)
+        .map(|(id, value)| format!("*{}*{}", id, b64.encode(value)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let score = rng.below(11) as f32;
+
+    let tests = vec![
+        Test {
+            name: "Correctness".to_string(),
+            tags: None,
+            score: Some(score),
+            number: "1.1".to_string(),
+            output: Some(format!("{} of 10 checks passed", score)),
+            status: if score == 10.0 { "passed" } else { "failed" }.to_string(),
+            max_score: Some(10.0),
+            extra_data: None,
+            visibility: None,
+            name_format: None,
+            output_format: None,
+        },
+        Test {
+            name: "Emissions".to_string(),
+            tags: None,
+            score: None,
+            number: "99.1".to_string(),
+            output: Some(emissions),
+            status: "passed".to_string(),
+            max_score: None,
+            extra_data: None,
+            visibility: None,
+            name_format: None,
+            output_format: None,
+        },
+    ];
+
+    // Submissions are spread over the week before a deadline of 2024-09-10 00:00
+    let minutes = 7 * 24 * 60 - minutes_before_deadline;
+    let created_at = format!(
+        "2024-09-{:02} {:02}:{:02}:00.000000000 -04:00",
+        3 + minutes / (24 * 60),
+        (minutes / 60) % 24,
+        minutes % 60
+    );
+
+    LatestSubmission {
+        submitters: vec![Submitter {
+            name: format!("Student {}", i),
+            sid: Some(sid(i)),
+            email: format!("student{}@example.edu", i),
+        }],
+        created_at,
+        score,
+        status: "processed".to_string(),
+        results: Some(Results::Processed(ProcessedResults {
+            score,
+            tests,
+            output: None,
+            extra_data: None,
+            visibility: "visible".to_string(),
+            leaderboard: vec![],
+            output_format: None,
+            execution_time: 1.0 + rng.unit() as f32,
+            test_name_format: None,
+            test_output_format: None,
+        })),
+        history: vec![],
+    }
+}
+
+/// Writes an export as a `submission_metadata.yml` file.
+pub fn write_export<T: AsRef<Path>>(export: &Export, path: T) -> Result<(), String> {
+    // Sort by key so the same config always writes the same file
+    let sorted: std::collections::BTreeMap<_, _> = export.iter().collect();
+    let data = serde_yaml::to_string(&sorted).map_err(|e| e.to_string())?;
+    fs::write(path, data).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradescope::types::SubmissionTrait;
+
+    #[test]
+    fn test_generate_plants_rings() {
+        let config = SynthConfig {
+            num_submissions: 50,
+            collision_rate: 0.0,
+            ..Default::default()
+        };
+        let (export, truth) = generate(&config);
+        assert_eq!(export.len(), 50);
+        assert_eq!(truth.rings.len(), 3);
+
+        // Same config, same export
+        assert_eq!(generate(&config).1, truth);
+
+        let by_sid: HashMap<&String, &LatestSubmission> = export
+            .values()
+            .map(|s| (s.submitters[0].sid.as_ref().unwrap(), s))
+            .collect();
+        for ring in &truth.rings {
+            let leader = by_sid[&ring[0]].parse_emissions();
+            for member in &ring[1..] {
+                let member = by_sid[member].parse_emissions();
+                let shared = leader
+                    .emissions()
+                    .filter(|e| member.emissions_map()[&e.id()].matches(e))
+                    .count();
+                assert_eq!(shared, config.ring_shared_ids);
+            }
+        }
+    }
+
+    #[test]
+    fn test_written_export_loads() {
+        let (export, _) = generate(&SynthConfig {
+            num_submissions: 5,
+            ..Default::default()
+        });
+        let path = std::env::temp_dir().join(format!("rufus-synth-{}.yml", std::process::id()));
+        write_export(&export, &path).unwrap();
+        let loaded = crate::gradescope::loaders::load_export(&path).unwrap();
+        let streamed = crate::gradescope::stream::stream_export(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), 5);
+        assert_eq!(streamed.len(), 5);
+    }
+}
@@ -0,0 +1,870 @@
+//! Runs `count` and `hunt` through the binary on the exports in `tests/fixtures`, checking
+//! their JSON output.
+
+use std::process::{Command, Output};
+
+use itertools::Itertools;
+use rufus::cli::report::{CountReport, DiagnoseReport, GroupReport, HuntReport, PseudonymReport};
+use rufus::rufus::{Decision, GroupIdentity, ReviewState};
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn rufus(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_rufus"))
+        .args(args)
+        .output()
+        .expect("failed to run rufus");
+    assert!(
+        output.status.success(),
+        "rufus {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn hunt(fixture_name: &str, extra_args: &[&str]) -> (HuntReport, String) {
+    let path = fixture(fixture_name);
+    let mut args = vec!["hunt", path.as_str(), "--format", "json"];
+    args.extend(extra_args);
+    let output = rufus(&args);
+    let report = serde_json::from_slice(&output.stdout).expect("hunt should print JSON");
+    (report, String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+fn member_sids(group: &GroupReport) -> Vec<&str> {
+    group
+        .members
+        .iter()
+        .map(|m| m.sid.as_deref().unwrap_or("?"))
+        .collect()
+}
+
+#[test]
+fn count_reports_every_fixture() {
+    let names = [
+        "latest.yml",
+        "historical.yml",
+        "failed.yml",
+        "group.yml",
+        "malformed.yml",
+    ];
+    let paths: Vec<String> = names.iter().map(|n| fixture(n)).collect();
+    let mut args = vec!["count", "--format", "json"];
+    args.extend(paths.iter().map(|p| p.as_str()));
+
+    let report: CountReport = serde_json::from_slice(&rufus(&args).stdout).unwrap();
+    let counts: Vec<usize> = report.files.iter().map(|f| f.submissions).collect();
+    assert_eq!(counts, vec![4, 2, 4, 3, 3]);
+    assert_eq!(report.total, 16);
+}
+
+#[test]
+fn hunt_groups_copied_emissions() {
+    let (report, _) = hunt("latest.yml", &["-k", "2"]);
+    assert_eq!(report.submissions, 4);
+    assert_eq!(report.emissions, 12);
+    assert_eq!(report.groups.len(), 1);
+
+    let group = &report.groups[0];
+    assert_eq!(group.on_ids, vec!["x", "y"]);
+    assert_eq!(member_sids(group), vec!["10000001", "10000002"]);
+    let values: Vec<&str> = group.emissions.iter().map(|e| e.value.as_str()).collect();
+    assert_eq!(values, vec!["1", "2"]);
+}
+
+#[test]
+fn hunt_defaults_to_every_emission() {
+    // Without -k, submissions must match on all three IDs
+    let (report, _) = hunt("latest.yml", &[]);
+    assert_eq!(report.k, 3);
+    assert!(report.groups.is_empty());
+}
+
+#[test]
+fn hunt_min_size_filters_groups() {
+    let (report, _) = hunt("latest.yml", &["-k", "1", "-m", "3"]);
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(report.groups[0].on_ids, vec!["x"]);
+    assert_eq!(
+        member_sids(&report.groups[0]),
+        vec!["10000001", "10000002", "10000004"]
+    );
+}
+
+#[test]
+fn hunt_ranks_groups_by_p_value() {
+    // x = 1 is shared by 3 of 4 students and y = 2 by 2 of 4, so the x group is more likely
+    // to be a coincidence: (3/4)^2 vs (2/4)^1
+    let (report, _) = hunt("latest.yml", &["-k", "1", "--sort-by", "p-value"]);
+    let ranked: Vec<(&str, f64)> = report
+        .groups
+        .iter()
+        .map(|g| (g.on_ids[0].as_str(), g.p_value.unwrap()))
+        .collect();
+    assert_eq!(ranked, vec![("y", 0.5), ("x", 0.5625)]);
+
+    let (report, _) = hunt("latest.yml", &["-k", "1", "--max-p-value", "0.55"]);
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(report.groups[0].on_ids, vec!["y"]);
+}
+
+#[test]
+fn hunt_sorts_and_truncates_groups() {
+    // A, B, and D share x; A and B (who submitted 30 minutes apart) also share y
+    let first = |sort_by: &str| {
+        let (report, _) = hunt("latest.yml", &["-k", "1", "--sort-by", sort_by]);
+        assert_eq!(report.groups.len(), 2);
+        report.groups[0].on_ids.clone()
+    };
+    assert_eq!(first("size"), vec!["x"]);
+    assert_eq!(first("matched-ids"), vec!["y"]);
+    assert_eq!(first("earliest"), vec!["x"]);
+    assert_eq!(first("spread"), vec!["y"]);
+
+    let (report, _) = hunt(
+        "latest.yml",
+        &["-k", "1", "--sort-by", "spread", "--top", "1"],
+    );
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(report.groups[0].on_ids, vec!["y"]);
+}
+
+#[test]
+fn hunt_filters_groups_by_submission_time() {
+    // A and B submitted 29 minutes apart on 9/10; D submitted two days earlier
+    let on_ids = |args: &[&str]| {
+        let mut all = vec!["-k", "1"];
+        all.extend(args);
+        let (report, _) = hunt("latest.yml", &all);
+        report
+            .groups
+            .iter()
+            .map(|g| g.on_ids.join(","))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(on_ids(&["--within", "30m"]), vec!["y"]);
+    assert_eq!(on_ids(&["--within", "29m"]), Vec::<String>::new());
+    assert_eq!(on_ids(&["--after", "2024-09-10 00:00 -04:00"]), vec!["y"]);
+    assert_eq!(
+        on_ids(&["--before", "2024-09-10T21:30:00-04:00"]),
+        Vec::<String>::new()
+    );
+
+    let (report, _) = hunt("latest.yml", &["-k", "2"]);
+    let timing = report.groups[0].timing.as_ref().unwrap();
+    assert_eq!(timing.spread_seconds, 29 * 60 + 22);
+    assert_eq!(timing.latest, "2024-09-10T21:45:02-04:00");
+}
+
+#[test]
+fn hunt_streaming_matches_full_load() {
+    let (full, _) = hunt("latest.yml", &["-k", "2"]);
+    let (streamed, _) = hunt("latest.yml", &["-k", "2", "--stream"]);
+    assert_eq!(full, streamed);
+}
+
+#[test]
+fn hunt_compares_only_latest_submissions() {
+    // The history holds different values, which must not be grouped
+    let (report, _) = hunt("historical.yml", &["-k", "2"]);
+    assert_eq!(report.submissions, 2);
+    assert_eq!(report.emissions, 4);
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(member_sids(&report.groups[0]), vec!["10000001", "10000002"]);
+}
+
+#[test]
+fn hunt_skips_failed_and_unprocessed_results() {
+    let (report, _) = hunt("failed.yml", &["-k", "2"]);
+    assert_eq!(report.submissions, 4);
+    assert_eq!(report.emissions, 4);
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(member_sids(&report.groups[0]), vec!["10000001", "10000004"]);
+}
+
+#[test]
+fn hunt_reports_every_group_submitter() {
+    let (report, _) = hunt("group.yml", &["-k", "2"]);
+    assert_eq!(report.submissions, 3);
+    assert_eq!(report.groups.len(), 1);
+    // Both partners of the group submission are reported alongside the copier
+    assert_eq!(
+        member_sids(&report.groups[0]),
+        vec!["10000001", "10000002", "10000003"]
+    );
+}
+
+#[test]
+fn hunt_skips_malformed_emissions() {
+    let (report, stderr) = hunt("malformed.yml", &["-k", "2"]);
+    assert_eq!(report.submissions, 3);
+    // Only x and y of the first two submissions parse
+    assert_eq!(report.emissions, 4);
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(report.groups[0].on_ids, vec!["x", "y"]);
+    assert!(stderr.contains("Format Error"));
+    assert!(stderr.contains("Decode Error"));
+}
+
+#[test]
+fn hunt_text_output_lists_groups() {
+    let output = rufus(&["hunt", &fixture("latest.yml"), "-k", "2", "-S"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("found 1 groups"));
+    assert!(stdout.contains("Group 1:"));
+    assert!(stdout.contains("Student A (SID: 10000001)"));
+    assert!(stdout.contains("Student B (SID: 10000002)"));
+}
+
+#[test]
+fn hunt_compares_member_scores() {
+    let (report, _) = hunt("latest.yml", &["-k", "2"]);
+    let group = &report.groups[0];
+    let scores: Vec<_> = group
+        .members
+        .iter()
+        .map(|m| (m.score, m.max_score))
+        .collect();
+    assert_eq!(
+        scores,
+        vec![(Some(10.0), Some(10.0)), (Some(8.0), Some(10.0))]
+    );
+    let summary = group.scores.as_ref().unwrap();
+    assert_eq!((summary.min, summary.max, summary.mean), (8.0, 10.0, 9.0));
+    // latest.yml's tests aren't tied to its emissions, so there's nothing to judge them by
+    assert!(!summary.all_full_marks && summary.identical_partial.is_none());
+
+    // In parents.yml, A and B both got 0/5 on "Part 1" (x's parent) with the same wrong x,
+    // and everyone passed "y"
+    let flags = |report: &HuntReport| -> Vec<_> {
+        report
+            .groups
+            .iter()
+            .map(|g| {
+                let scores = g.scores.as_ref().unwrap();
+                (
+                    g.on_ids.clone(),
+                    scores.all_full_marks,
+                    scores.identical_partial,
+                )
+            })
+            .collect()
+    };
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+    let (report, _) = hunt("parents.yml", &["-k", "1"]);
+    assert_eq!(
+        flags(&report),
+        vec![
+            (ids(&["y"]), true, None),
+            (ids(&["x"]), false, Some((0.0, 5.0)))
+        ]
+    );
+    let (report, _) = hunt("parents.yml", &["-k", "2"]);
+    assert_eq!(
+        flags(&report),
+        vec![(ids(&["x", "y"]), false, Some((5.0, 10.0)))]
+    );
+    let (report, _) = hunt("parents.yml", &["-k", "1", "--exclude-full-marks"]);
+    assert_eq!(flags(&report), vec![(ids(&["x"]), false, Some((0.0, 5.0)))]);
+
+    // B's x of 1.2 only matches A's 1 after rounding, so it isn't the same mistake
+    let parents = std::fs::read_to_string(fixture("parents.yml")).unwrap();
+    let a_x = parents.find("*x*MQ==").unwrap() + 1;
+    let rewritten = format!(
+        "{}{}",
+        &parents[..a_x],
+        parents[a_x..].replacen("*x*MQ==", "*x*MS4y", 1)
+    );
+    let path = std::env::temp_dir().join(format!("rufus-cli-scores-{}.yml", std::process::id()));
+    std::fs::write(&path, rewritten).unwrap();
+    let output = rufus(&[
+        "hunt",
+        path.to_str().unwrap(),
+        "--format",
+        "json",
+        "-k",
+        "1",
+        "--normalize",
+        "x=numeric:1",
+    ]);
+    std::fs::remove_file(&path).unwrap();
+    let report: HuntReport = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(flags(&report)[1], (ids(&["x"]), false, None));
+}
+
+#[test]
+fn hunt_flags_shared_incorrect_output() {
+    // A and B both emitted a wrong x for "Part 1" (tagged `emission:x`); everyone got "y" right
+    let (report, _) = hunt("parents.yml", &["-k", "1"]);
+    let emissions: Vec<_> = report
+        .groups
+        .iter()
+        .flat_map(|g| &g.emissions)
+        .map(|e| (e.id.as_str(), e.parent_test.as_deref(), e.shared_incorrect))
+        .collect();
+    assert_eq!(
+        emissions,
+        vec![("y", Some("y"), false), ("x", Some("Part 1"), true)]
+    );
+}
+
+#[test]
+fn hunt_compares_failed_outputs_when_asked() {
+    // Groups of the same size can come out in any order
+    let ids = |report: &HuntReport| {
+        report
+            .groups
+            .iter()
+            .map(|g| g.on_ids.join(","))
+            .sorted()
+            .collect::<Vec<_>>()
+    };
+    let (report, _) = hunt("parents.yml", &["-k", "1"]);
+    assert_eq!(ids(&report), vec!["x", "y"]);
+
+    // A and B's failed "Part 1" (test 1.1) printed the same output
+    let (report, _) = hunt("parents.yml", &["-k", "1", "--failed-outputs"]);
+    assert_eq!(ids(&report), vec!["test:1.1", "x", "y"]);
+    let group = report
+        .groups
+        .iter()
+        .find(|g| g.on_ids == ["test:1.1"])
+        .unwrap();
+    assert_eq!(member_sids(group), vec!["10000001", "10000002"]);
+    assert_eq!(group.emissions[0].value, "Wrong answer");
+    assert!(group.emissions[0].shared_incorrect);
+
+    let (streamed, _) = hunt("parents.yml", &["-k", "1", "--failed-outputs", "--stream"]);
+    assert_eq!(ids(&streamed), ids(&report));
+    let streamed_group = streamed.groups.iter().find(|g| g.on_ids == ["test:1.1"]);
+    assert_eq!(streamed_group, Some(group));
+}
+
+#[test]
+fn hunt_exports_collusion_graph() {
+    let roster = std::env::temp_dir().join(format!("rufus-cli-roster-{}.csv", std::process::id()));
+    std::fs::write(&roster, "sid,section\n10000001,101\n10000002,102\n").unwrap();
+    let latest = fixture("latest.yml");
+    let graph = |format: &str| {
+        let args = [
+            "hunt",
+            latest.as_str(),
+            "-k",
+            "1",
+            "--format",
+            format,
+            "--roster",
+            roster.to_str().unwrap(),
+        ];
+        String::from_utf8(rufus(&args).stdout).unwrap()
+    };
+    let (dot, graphml) = (graph("dot"), graph("graphml"));
+    std::fs::remove_file(&roster).unwrap();
+
+    // A, B, and D all share x; A and B also share y
+    assert!(dot.starts_with("graph rufus {"));
+    assert!(dot.contains(
+        "\"10000001\" [label=\"Student A (10000001)\", fillcolor=\"#1f77b4\", section=\"101\"];"
+    ));
+    assert!(dot.contains("\"10000004\" [label=\"Student D (10000004)\", fillcolor=\"#bbbbbb\"];"));
+    assert!(dot.contains("\"10000001\" -- \"10000002\" [weight=2,"));
+    assert_eq!(dot.matches(" -- ").count(), 3);
+    assert_eq!(graphml.matches("<node ").count(), 3);
+    assert_eq!(graphml.matches("<edge ").count(), 3);
+    assert!(graphml.contains("<data key=\"section\">102</data>"));
+}
+
+#[test]
+fn hunt_remembers_reviewed_groups() {
+    let identity = |members: &[&str], emissions: &[(&str, &str)]| {
+        GroupIdentity::new(
+            members.iter().map(|m| m.to_string()),
+            emissions
+                .iter()
+                .map(|(id, value)| (id.to_string(), value.to_string())),
+        )
+    };
+    let mut state = ReviewState::default();
+    state.decide(
+        &identity(&["10000001", "10000002"], &[("x", "1"), ("y", "2")]),
+        Decision::Dismissed,
+    );
+    state.decide(
+        &identity(&["10000001", "10000002"], &[("x", "1")]),
+        Decision::Confirmed,
+    );
+    let path = std::env::temp_dir().join(format!("rufus-cli-review-{}.yml", std::process::id()));
+    state.save(&path).unwrap();
+    let review_file = path.to_str().unwrap();
+
+    // The dismissed group hasn't changed, so it's hidden unless asked for
+    let (report, _) = hunt("latest.yml", &["-k", "2", "--review-file", review_file]);
+    assert!(report.groups.is_empty());
+    assert_eq!(report.hidden_reviewed, 1);
+    let (report, _) = hunt(
+        "latest.yml",
+        &["-k", "2", "--review-file", review_file, "--show-reviewed"],
+    );
+    let review = report.groups[0].review.as_ref().unwrap();
+    assert_eq!(review.status, "reviewed");
+    assert_eq!(review.decision, Some(Decision::Dismissed));
+
+    // Student D joined the confirmed group on x
+    let (report, _) = hunt("latest.yml", &["-k", "1", "--review-file", review_file]);
+    std::fs::remove_file(&path).unwrap();
+    let grown = report
+        .groups
+        .iter()
+        .find(|g| g.on_ids == vec!["x"])
+        .unwrap();
+    let review = grown.review.as_ref().unwrap();
+    assert_eq!(review.status, "grown");
+    assert_eq!(review.decision, Some(Decision::Confirmed));
+    assert_eq!(review.added_members, vec!["10000004"]);
+    // The same pair matching on y alone is a change from both reviews
+    let changed = report
+        .groups
+        .iter()
+        .find(|g| g.on_ids == vec!["y"])
+        .unwrap();
+    assert_eq!(changed.review.as_ref().unwrap().status, "changed");
+}
+
+#[test]
+fn hunt_top_skips_hidden_reviewed_groups() {
+    // The largest group (A, B, and D on x) was already reviewed
+    let mut state = ReviewState::default();
+    state.decide(
+        &GroupIdentity::new(
+            ["10000001", "10000002", "10000004"].map(String::from),
+            [("x".to_string(), "1".to_string())],
+        ),
+        Decision::Dismissed,
+    );
+    let path = std::env::temp_dir().join(format!("rufus-cli-top-{}.yml", std::process::id()));
+    state.save(&path).unwrap();
+
+    let (report, _) = hunt(
+        "latest.yml",
+        &[
+            "-k",
+            "1",
+            "--top",
+            "1",
+            "--review-file",
+            path.to_str().unwrap(),
+        ],
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(report.hidden_reviewed, 1);
+    let on_ids: Vec<_> = report.groups.iter().map(|g| g.on_ids.join(",")).collect();
+    assert_eq!(on_ids, vec!["y"]);
+}
+
+#[test]
+fn anonymized_hunt_maps_back_with_the_key() {
+    let (report, _) = hunt("latest.yml", &["-k", "2", "--anonymize", "course key"]);
+    let group = &report.groups[0];
+    for member in &group.members {
+        assert!(member.name.starts_with("anon-"));
+        assert_eq!(member.sid.as_ref(), Some(&member.name));
+        assert!(member.email.is_empty());
+    }
+
+    // The same key gives the same pseudonyms, which map back to the copied pair
+    let (rerun, _) = hunt("latest.yml", &["-k", "2", "--anonymize", "course key"]);
+    assert_eq!(rerun.groups[0].members, group.members);
+    let pseudonyms = member_sids(group);
+    let path = fixture("latest.yml");
+    let mut args = vec!["deanonymize", path.as_str(), "--key", "course key"];
+    args.extend(pseudonyms.iter().flat_map(|p| ["-p", *p]));
+    args.extend(["--format", "json"]);
+    let students: Vec<PseudonymReport> = serde_json::from_slice(&rufus(&args).stdout).unwrap();
+    let mut sids: Vec<&str> = students.iter().map(|s| s.sid.as_deref().unwrap()).collect();
+    sids.sort();
+    assert_eq!(sids, vec!["10000001", "10000002"]);
+}
+
+#[test]
+fn hunt_compares_values_by_schema() {
+    // B's z becomes 3.0 (A's is 3), and D's y isn't an integer
+    let export = std::fs::read_to_string(fixture("latest.yml"))
+        .unwrap()
+        .replace("*z*OQ==", "*z*My4w")
+        .replace("*y*Nw==", "*y*c2V2ZW4=");
+    let dir = std::env::temp_dir().join(format!("rufus-cli-schema-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (path, schema) = (dir.join("export.yml"), dir.join("schema.yml"));
+    std::fs::write(&path, export).unwrap();
+    std::fs::write(
+        &schema,
+        "z:\n  type: float\n  tolerance: 0.1\ny:\n  type: integer\n",
+    )
+    .unwrap();
+    let hunt_schema = |extra_args: &[&str]| {
+        let mut args = vec![
+            "hunt",
+            path.to_str().unwrap(),
+            "--format",
+            "json",
+            "-k",
+            "1",
+            "--schema",
+            schema.to_str().unwrap(),
+        ];
+        args.extend(extra_args);
+        let output = rufus(&args);
+        let report: HuntReport = serde_json::from_slice(&output.stdout).unwrap();
+        (report, String::from_utf8_lossy(&output.stderr).to_string())
+    };
+    let (report, stderr) = hunt_schema(&[]);
+    // --normalize takes precedence over the schema's tolerance
+    let (exact, _) = hunt_schema(&["--normalize", "z=trim"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(stderr.contains("Error parsing emission \"y\" as integer"));
+    let z = report
+        .groups
+        .iter()
+        .find(|g| g.on_ids == vec!["z"])
+        .unwrap();
+    assert_eq!(
+        member_sids(z).iter().sorted().collect_vec(),
+        vec![&"10000001", &"10000002"]
+    );
+    assert_eq!(
+        z.emissions[0].raw_values.iter().sorted().collect_vec(),
+        vec!["3", "3.0"]
+    );
+    assert!(exact.groups.iter().all(|g| g.on_ids != vec!["z"]));
+}
+
+#[test]
+fn hunt_flags_another_students_expected_output() {
+    // A's x is correct, B and D turned in A's, and C turned in B's
+    let path = std::env::temp_dir().join(format!("rufus-cli-expected-{}.csv", std::process::id()));
+    std::fs::write(
+        &path,
+        "sid,emission_id,value\n10000001,x,1\n10000002,x,4\n10000003,x,7\n10000004,x,9\n",
+    )
+    .unwrap();
+    let (report, _) = hunt("latest.yml", &["--expected", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+
+    let matches: Vec<_> = report
+        .foreign_seed_matches
+        .unwrap()
+        .iter()
+        .map(|m| {
+            (
+                m.submitters[0].sid.clone().unwrap(),
+                m.emission_id.clone(),
+                m.matches_sids.clone(),
+            )
+        })
+        .sorted()
+        .collect();
+    let found =
+        |sid: &str, matches: &str| (sid.to_string(), "x".to_string(), vec![matches.to_string()]);
+    assert_eq!(
+        matches,
+        vec![
+            found("10000002", "10000001"),
+            found("10000003", "10000002"),
+            found("10000004", "10000001"),
+        ]
+    );
+}
+
+#[test]
+fn hunt_verifies_signed_emissions() {
+    use rufus::rufus::{format_signed_emission, sign_emission};
+
+    // A signs x and C signs y; B copies A's signed x and edits a signed z. Everything else is
+    // left unsigned
+    let signed = |id: &str, value: &str, nonce: &str| {
+        format_signed_emission(b"course secret", id, value, nonce)
+    };
+    let tag = sign_emission(b"course secret", "z", "3", "n2");
+    let export = std::fs::read_to_string(fixture("latest.yml"))
+        .unwrap()
+        .replacen("*x*MQ==", &signed("x", "1", "n1"), 2)
+        .replacen("*z*OQ==", &format!("*z*OQ==*n2*{}", tag), 1)
+        .replacen("*y*NQ==", &signed("y", "5", "n3"), 1);
+    let path = std::env::temp_dir().join(format!("rufus-cli-signed-{}.yml", std::process::id()));
+    std::fs::write(&path, export).unwrap();
+
+    let output = rufus(&[
+        "hunt",
+        path.to_str().unwrap(),
+        "--signing-secret",
+        "course secret",
+        "--format",
+        "json",
+    ]);
+    std::fs::remove_file(&path).unwrap();
+    let report: HuntReport = serde_json::from_slice(&output.stdout).unwrap();
+    let findings = report.authenticity_findings.unwrap();
+    let flagged: Vec<_> = findings
+        .iter()
+        .filter(|f| f.issue != "forged")
+        .map(|f| {
+            (
+                f.submitters[0].sid.as_deref().unwrap(),
+                f.emission_id.as_str(),
+                f.issue.as_str(),
+                f.replayed_by
+                    .iter()
+                    .map(|m| m.sid.as_deref().unwrap())
+                    .collect(),
+            )
+        })
+        .sorted()
+        .collect();
+    assert_eq!(
+        flagged,
+        vec![
+            ("10000001", "x", "replayed", vec!["10000002"]),
+            ("10000002", "x", "replayed", vec!["10000001"]),
+            ("10000002", "z", "tampered", vec![]),
+        ]
+    );
+    // The other 8 of the 12 emissions are unsigned
+    assert_eq!(findings.iter().filter(|f| f.issue == "forged").count(), 8);
+}
+
+/// Writes a ZIP export of `latest.yml`, with one `solution.py` per submission (by key).
+fn zip_export(path: &std::path::Path, solutions: [&str; 4]) {
+    use std::io::Write;
+
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    let metadata = std::fs::read_to_string(fixture("latest.yml")).unwrap();
+    writer
+        .start_file("assignment_1_export/submission_metadata.yml", options)
+        .unwrap();
+    writer.write_all(metadata.as_bytes()).unwrap();
+    for (key, solution) in ["1001", "1002", "1003", "1004"].iter().zip(solutions) {
+        writer
+            .start_file(
+                format!("assignment_1_export/submission_{}/solution.py", key),
+                options,
+            )
+            .unwrap();
+        writer.write_all(solution.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[test]
+fn packet_summarizes_group_and_copies_submissions() {
+    let dir = std::env::temp_dir().join(format!("rufus-cli-packet-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let export = dir.join("export.zip");
+    zip_export(&export, ["1001", "1002", "1003", "1004"]);
+    let packet = dir.join("packet");
+
+    rufus(&[
+        "packet",
+        export.to_str().unwrap(),
+        "-k",
+        "2",
+        "--group",
+        "1",
+        "-o",
+        packet.to_str().unwrap(),
+    ]);
+    let summary = std::fs::read_to_string(packet.join("summary.md")).unwrap();
+    let copied = |key: &str| packet.join(format!("submissions/submission_{}/solution.py", key));
+    let result = (
+        summary.contains("| Student A | 10000001 | a@example.edu |"),
+        summary.contains("3 of the 4 submissions that emitted `x` have this value"),
+        summary.contains(&format!("Rufus version: {}", env!("CARGO_PKG_VERSION"))),
+        std::fs::read_to_string(copied("1001")).ok(),
+        copied("1003").exists(),
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(result, (true, true, true, Some("1001".to_string()), false));
+}
+
+#[test]
+fn packet_keeps_anonymized_students_anonymous() {
+    let dir = std::env::temp_dir().join(format!("rufus-cli-packet-anon-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let export = dir.join("export.zip");
+    zip_export(&export, ["1001", "1002", "1003", "1004"]);
+    let packet = dir.join("packet");
+
+    let output = rufus(&[
+        "packet",
+        export.to_str().unwrap(),
+        "-k",
+        "1",
+        "--group",
+        "1",
+        "--anonymize",
+        "course key",
+        "-o",
+        packet.to_str().unwrap(),
+    ]);
+    let mut contents = vec![];
+    let mut dirs = vec![packet.clone()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            contents.push(path.to_string_lossy().to_string());
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                contents.push(std::fs::read_to_string(path).unwrap());
+            }
+        }
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("copied into the packet aren't anonymized"));
+    let contents = contents.join("\n");
+    assert!(contents.contains("anon-"));
+    assert!(contents.contains("solution.py"));
+    for identity in [
+        "Student A",
+        "Student B",
+        "Student C",
+        "Student D",
+        "example.edu",
+        "1000000",
+    ] {
+        assert!(!contents.contains(identity), "{} leaked", identity);
+    }
+}
+
+#[test]
+fn packet_numbers_groups_like_hunt() {
+    let dir = std::env::temp_dir().join(format!("rufus-cli-packet-hunt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let export = dir.join("export.zip");
+    zip_export(&export, ["1001", "1002", "1003", "1004"]);
+    let packet = dir.join("packet");
+
+    // Review the largest group (A, B, and D on x) as an anonymized hunt shows it, so it's
+    // hidden and y becomes group 1
+    let anonymized = ["-k", "1", "--anonymize", "course key", "--sort-by", "name"];
+    let (report, _) = hunt("latest.yml", &anonymized);
+    assert_eq!(report.groups[0].on_ids, vec!["x"]);
+    let mut state = ReviewState::default();
+    state.decide(&report.groups[0].identity(), Decision::Dismissed);
+    let review_file = dir.join("reviews.yml");
+    state.save(&review_file).unwrap();
+
+    let mut options = anonymized.to_vec();
+    options.extend(["--review-file", review_file.to_str().unwrap()]);
+    let (report, _) = hunt("latest.yml", &options);
+    let mut args = vec!["packet", export.to_str().unwrap(), "--group", "1"];
+    args.extend(options);
+    args.extend(["--format", "html", "-o", packet.to_str().unwrap()]);
+    rufus(&args);
+    let summary = std::fs::read_to_string(packet.join("summary.html")).unwrap();
+    let copied = packet
+        .join("submissions/submission_1001/solution.py")
+        .exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.groups[0].on_ids, vec!["y"]);
+    assert!(summary.starts_with("<!DOCTYPE html>"));
+    assert!(summary.contains("identical values for 1 emission IDs (y)"));
+    assert!(summary.contains(&report.groups[0].members[0].name));
+    assert!(summary.contains("<table>"));
+    assert!(copied);
+}
+
+#[test]
+fn hunt_compares_code_of_group_members() {
+    let dir = std::env::temp_dir().join(format!("rufus-cli-code-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let export = dir.join("export.zip");
+    // B renamed A's variables and reworded the comment; D wrote their own
+    zip_export(
+        &export,
+        [
+            "def solve(A, b):\n    # elimination\n    x = gauss(A, b)\n    return x, norm(A @ x - b)\n",
+            "def answer(M, v):\n    # my own work\n    y = gauss(M, v)\n    return y, norm(M @ y - v)\n",
+            "print(1)\n",
+            "total = 0\nfor row in rows:\n    for value in row:\n        total += value\nprint(total)\n",
+        ],
+    );
+    let hunt_code = |extra_args: &[&str]| {
+        let mut args = vec![
+            "hunt",
+            export.to_str().unwrap(),
+            "--format",
+            "json",
+            "-k",
+            "1",
+            "--code-similarity",
+        ];
+        args.extend(extra_args);
+        serde_json::from_slice::<HuntReport>(&rufus(&args).stdout).unwrap()
+    };
+    let report = hunt_code(&[]);
+    let streamed = hunt_code(&["--stream"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(streamed, report);
+    let pairs: Vec<Vec<_>> = report
+        .groups
+        .iter()
+        .map(|g| {
+            g.code_similarity
+                .iter()
+                .map(|p| (p.first.as_str(), p.second.as_str(), p.similarity))
+                .collect()
+        })
+        .collect();
+    // Only group members are compared, most similar first
+    assert_eq!(pairs[0].len(), 3);
+    assert_eq!(pairs[0][0], ("Student A", "Student B", 1.0));
+    assert!(pairs[0][1..]
+        .iter()
+        .all(|(_, _, similarity)| *similarity < 0.5));
+    assert_eq!(pairs[1], vec![("Student A", "Student B", 1.0)]);
+}
+
+#[test]
+fn diagnose_explains_missing_emissions() {
+    let output = rufus(&["diagnose", &fixture("failed.yml"), "--format", "json"]);
+    let report: DiagnoseReport = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report.submissions, 4);
+    assert_eq!(report.issue_counts["autograder_failure"], 1);
+    assert_eq!(report.issue_counts["unprocessed"], 1);
+
+    let failure = report
+        .diagnoses
+        .iter()
+        .flat_map(|d| &d.issues)
+        .find(|i| i.category == "autograder_failure")
+        .unwrap();
+    assert!(failure.details[0].contains("No module named numpy"));
+}
+
+#[test]
+fn coverage_writes_matrix_as_csv() {
+    let output = rufus(&["coverage", &fixture("malformed.yml"), "--format", "csv"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.remove(0), "name,sid,email,x,y,z");
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            "Student A,10000001,a@example.edu,present,present,decode_error",
+            "Student B,10000002,b@example.edu,present,present,decode_error",
+            "Student C,10000003,c@example.edu,absent,absent,absent",
+        ]
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("\"z\" emitted by 0 of 3"));
+}